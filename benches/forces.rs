@@ -0,0 +1,56 @@
+//! Compares accumulating forces in `Forces` with the string keyed map it replaced,
+//! doing what a physics step does for every body: apply gravity and thrust, then sum them up.
+
+use bevy::math::Vec3;
+use bevy::utils::HashMap;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use star_fighters::physics::{gravity, ForceSource, Forces};
+
+const BODIES: [usize; 3] = [1_000, 5_000, 20_000];
+const STAR: (f32, Vec3) = (10_000., Vec3::ZERO);
+
+fn positions(count: usize) -> Vec<Vec3> {
+    (0..count)
+        .map(|index| Vec3::new(100. + index as f32, 50. - index as f32 * 0.5, 0.))
+        .collect()
+}
+
+fn accumulate_forces(c: &mut Criterion) {
+    let mut group = c.benchmark_group("accumulate_forces");
+    for count in BODIES {
+        let positions = positions(count);
+
+        let mut maps = vec![HashMap::<String, Vec3>::default(); count];
+        group.bench_with_input(BenchmarkId::new("string_map", count), &count, |b, _| {
+            b.iter(|| {
+                for (position, forces) in positions.iter().zip(maps.iter_mut()) {
+                    forces.insert(
+                        "gravity".to_string(),
+                        gravity(*position, 1., std::iter::once(STAR)),
+                    );
+                    forces.insert("thrust".to_string(), Vec3::Y * 200.);
+                    black_box(forces.values().sum::<Vec3>());
+                }
+            })
+        });
+
+        let mut typed = vec![Forces::default(); count];
+        group.bench_with_input(BenchmarkId::new("typed", count), &count, |b, _| {
+            b.iter(|| {
+                for (position, forces) in positions.iter().zip(typed.iter_mut()) {
+                    forces.clear();
+                    forces.set(
+                        ForceSource::Gravity,
+                        gravity(*position, 1., std::iter::once(STAR)),
+                    );
+                    forces.set(ForceSource::Thrust, Vec3::Y * 200.);
+                    black_box(forces.net());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, accumulate_forces);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+//! Compares the Barnes-Hut approximation of N-body gravity with the exact O(n²) sum.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::random_bodies;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use star_fighters::physics::barnes_hut::{exact_gravity, QuadTree, THETA};
+
+const BODIES: [usize; 3] = [500, 2_000, 5_000];
+
+fn n_body_gravity(c: &mut Criterion) {
+    let mut group = c.benchmark_group("n_body_gravity");
+    group.sample_size(10);
+    for count in BODIES {
+        let bodies = random_bodies(count);
+        group.bench_with_input(BenchmarkId::new("exact", count), &bodies, |b, bodies| {
+            b.iter(|| {
+                for body in bodies {
+                    black_box(exact_gravity(bodies, body.position));
+                }
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("barnes_hut", count),
+            &bodies,
+            |b, bodies| {
+                b.iter(|| {
+                    let tree = QuadTree::new(bodies);
+                    for body in bodies {
+                        black_box(tree.gravity(body.position, THETA));
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, n_body_gravity);
+criterion_main!(benches);
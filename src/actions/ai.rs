@@ -0,0 +1,119 @@
+//! Computer pilots, filling in the actions of the players they fly for.
+
+use super::Actions;
+use crate::match_setup::MatchConfig;
+use crate::physics::{Collider, Star, Velocity};
+use crate::player::{Guns, Player, Projectile};
+use crate::teams::TeamMode;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Who flies the ship of a player.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pilot {
+    #[default]
+    Human,
+    Ai,
+}
+
+impl Pilot {
+    pub fn cycle(&mut self) {
+        *self = match self {
+            Pilot::Human => Pilot::Ai,
+            Pilot::Ai => Pilot::Human,
+        };
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Pilot::Human => "Human",
+            Pilot::Ai => "AI",
+        }
+    }
+}
+
+/// Room kept around stars, on top of their collider.
+const STAR_MARGIN: f32 = 150.;
+/// How many seconds ahead pilots look when checking whether they're falling into a star.
+const LOOKAHEAD: f32 = 1.;
+/// How sharply pilots turn towards where they want to point.
+const TURN_GAIN: f32 = 3.;
+/// Radians off target at which pilots still fire.
+const AIM_TOLERANCE: f32 = 0.1;
+/// Radians off course at which pilots still thrust.
+const THRUST_TOLERANCE: f32 = 0.4;
+const FIRE_RANGE: f32 = 700.;
+/// Pilots speed up towards their target until they're this fast.
+const CRUISE_SPEED: f32 = 150.;
+/// Pilots stop closing in once they're this close to their target.
+const CLOSE_RANGE: f32 = 250.;
+
+/// Flies every ship with an AI [`Pilot`]: it turns away from stars it's falling into,
+/// and otherwise aims where the closest enemy is headed and fires once lined up.
+pub fn set_ai_actions(
+    mut actions: ResMut<Actions>,
+    config: Res<MatchConfig>,
+    team_mode: Res<TeamMode>,
+    ships: Query<(Entity, &Transform, &Velocity, &Guns, &Player)>,
+    targets: Query<
+        (Entity, &Transform, &Velocity, &Collider, Option<&Player>),
+        Without<Projectile>,
+    >,
+    stars: Query<(&Transform, &Collider), With<Star>>,
+) {
+    for (ship, transform, velocity, guns, player) in &ships {
+        if config.pilots[player.index()] != Pilot::Ai {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let velocity = velocity.0.truncate();
+
+        let danger = stars.iter().find_map(|(star_transform, collider)| {
+            let away = position - star_transform.translation.truncate();
+            let distance = away.length();
+            let closing_speed = -velocity.dot(away) / distance;
+            let safe_distance =
+                collider.dimensions.max_element() / 2. + STAR_MARGIN + closing_speed * LOOKAHEAD;
+            (distance > 0. && distance < safe_distance).then_some(away)
+        });
+
+        let target = targets
+            .iter()
+            .filter(|(entity, _, _, collider, target_player)| {
+                *entity != ship
+                    && collider.destroyable
+                    && target_player.is_none_or(|target_player| {
+                        team_mode.side(target_player.index()) != team_mode.side(player.index())
+                    })
+            })
+            .min_by(|(_, first, _, _, _), (_, second, _, _, _)| {
+                let distance = |other: &Transform| other.translation.truncate().distance(position);
+                distance(first).total_cmp(&distance(second))
+            });
+
+        let player_actions = &mut actions.player_actions[player.index()];
+        *player_actions = default();
+        let (heading, thrust, fire) = if let Some(away) = danger {
+            (away, true, false)
+        } else if let Some((_, target_transform, target_velocity, _, _)) = target {
+            let offset = target_transform.translation.truncate() - position;
+            let distance = offset.length();
+            // Shots inherit the ship's velocity, so lead with the relative velocity
+            let muzzle_speed = guns
+                .weapons()
+                .next()
+                .map_or(f32::MAX, |weapon| weapon.muzzle_speed);
+            let lead = (target_velocity.0.truncate() - velocity) * distance / muzzle_speed;
+            let heading = offset + lead;
+            let closing_in = velocity.length() < CRUISE_SPEED && distance > CLOSE_RANGE;
+            (heading, closing_in, distance < FIRE_RANGE)
+        } else {
+            continue;
+        };
+        let angle = transform.up().truncate().angle_between(heading);
+        let rotation = (angle * TURN_GAIN).clamp(-1., 1.);
+        player_actions.rotation = (rotation != 0.).then_some(rotation);
+        player_actions.thrust = thrust && angle.abs() < THRUST_TOLERANCE;
+        player_actions.fire = fire && angle.abs() < AIM_TOLERANCE;
+    }
+}
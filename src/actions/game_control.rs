@@ -1,4 +1,5 @@
-use bevy::prelude::{Input, KeyCode, Res};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
 
 pub enum GameControl {
     Up,
@@ -6,58 +7,176 @@ pub enum GameControl {
     Left,
     Right,
     Fire,
+    StrafeLeft,
+    StrafeRight,
 }
 
 impl GameControl {
-    pub fn pressed(&self, keyboard_input: &Res<Input<KeyCode>>, player_number: u8) -> bool {
+    /// The key bound to this control for the given player.
+    /// Every player gets their own zone of the keyboard.
+    fn key(&self, player_number: u8) -> KeyCode {
+        let keys = match player_number {
+            1 => [
+                KeyCode::W,
+                KeyCode::S,
+                KeyCode::A,
+                KeyCode::D,
+                KeyCode::Space,
+                KeyCode::Q,
+                KeyCode::E,
+            ],
+            2 => [
+                KeyCode::Up,
+                KeyCode::Down,
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::ControlRight,
+                KeyCode::Delete,
+                KeyCode::PageDown,
+            ],
+            3 => [
+                KeyCode::I,
+                KeyCode::K,
+                KeyCode::J,
+                KeyCode::L,
+                KeyCode::U,
+                KeyCode::H,
+                KeyCode::Semicolon,
+            ],
+            _ => [
+                KeyCode::Numpad8,
+                KeyCode::Numpad5,
+                KeyCode::Numpad4,
+                KeyCode::Numpad6,
+                KeyCode::Numpad0,
+                KeyCode::Numpad7,
+                KeyCode::Numpad9,
+            ],
+        };
         match self {
-            GameControl::Up => {
-                if player_number == 1 {
-                    keyboard_input.pressed(KeyCode::W)
-                } else {
-                    keyboard_input.pressed(KeyCode::Up)
-                }
-            }
-            GameControl::Down => {
-                if player_number == 1 {
-                    keyboard_input.pressed(KeyCode::S)
-                } else {
-                    keyboard_input.pressed(KeyCode::Down)
-                }
-            }
-            GameControl::Left => {
-                if player_number == 1 {
-                    keyboard_input.pressed(KeyCode::A)
-                } else {
-                    keyboard_input.pressed(KeyCode::Left)
-                }
-            }
-            GameControl::Right => {
-                if player_number == 1 {
-                    keyboard_input.pressed(KeyCode::D)
-                } else {
-                    keyboard_input.pressed(KeyCode::Right)
-                }
-            }
-            GameControl::Fire => {
-                if player_number == 1 {
-                    keyboard_input.pressed(KeyCode::Space)
-                } else {
-                    keyboard_input.pressed(KeyCode::ControlRight)
-                }
-            }
+            GameControl::Up => keys[0],
+            GameControl::Down => keys[1],
+            GameControl::Left => keys[2],
+            GameControl::Right => keys[3],
+            GameControl::Fire => keys[4],
+            GameControl::StrafeLeft => keys[5],
+            GameControl::StrafeRight => keys[6],
+        }
+    }
+
+    /// The gamepad buttons bound to this control, the same for every player.
+    /// Gamepads strafe with the right stick instead of buttons.
+    fn gamepad_buttons(&self) -> &'static [GamepadButtonType] {
+        match self {
+            GameControl::Up => &[GamepadButtonType::South, GamepadButtonType::RightTrigger2],
+            GameControl::Down => &[GamepadButtonType::DPadDown, GamepadButtonType::LeftTrigger2],
+            GameControl::Left => &[GamepadButtonType::DPadLeft],
+            GameControl::Right => &[GamepadButtonType::DPadRight],
+            GameControl::Fire => &[GamepadButtonType::West, GamepadButtonType::RightTrigger],
+            GameControl::StrafeLeft | GameControl::StrafeRight => &[],
+        }
+    }
+}
+
+/// Stick deflection below this is ignored, so worn sticks don't make ships spin.
+const STICK_DEAD_ZONE: f32 = 0.15;
+
+/// Keyboard and gamepad input, mapped to the controls of every player.
+/// Gamepads are handed out to players in the order they were connected,
+/// and players can use their keyboard zone at the same time.
+#[derive(SystemParam)]
+pub struct PlayerInput<'w> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl<'w> PlayerInput<'w> {
+    fn gamepad(&self, player_number: u8) -> Option<Gamepad> {
+        let mut gamepads: Vec<Gamepad> = self.gamepads.iter().collect();
+        gamepads.sort_by_key(|gamepad| gamepad.id);
+        gamepads.get(player_number as usize - 1).copied()
+    }
+
+    fn gamepad_buttons(
+        &self,
+        control: &GameControl,
+        player_number: u8,
+    ) -> impl Iterator<Item = GamepadButton> {
+        let gamepad = self.gamepad(player_number);
+        control
+            .gamepad_buttons()
+            .iter()
+            .filter_map(move |button_type| {
+                gamepad.map(|gamepad| GamepadButton::new(gamepad, *button_type))
+            })
+    }
+
+    pub fn pressed(&self, control: GameControl, player_number: u8) -> bool {
+        self.keyboard_input.pressed(control.key(player_number))
+            || self
+                .gamepad_buttons(&control, player_number)
+                .any(|button| self.gamepad_buttons.pressed(button))
+    }
+
+    pub fn just_pressed(&self, control: GameControl, player_number: u8) -> bool {
+        self.keyboard_input.just_pressed(control.key(player_number))
+            || self
+                .gamepad_buttons(&control, player_number)
+                .any(|button| self.gamepad_buttons.just_pressed(button))
+    }
+
+    /// Counterclockwise rotation from -1 to 1, from the turn controls or the left stick.
+    pub fn rotation(&self, player_number: u8) -> f32 {
+        -self.axis(
+            GameControl::Right,
+            GameControl::Left,
+            GamepadAxisType::LeftStickX,
+            player_number,
+        )
+    }
+
+    /// Sideways thrust from -1 (left) to 1 (right), from the strafe controls or the right stick.
+    pub fn strafe(&self, player_number: u8) -> f32 {
+        self.axis(
+            GameControl::StrafeRight,
+            GameControl::StrafeLeft,
+            GamepadAxisType::RightStickX,
+            player_number,
+        )
+    }
+
+    /// From -1 to 1: 1 while `positive` is pressed, -1 while `negative` is,
+    /// or the deflection of the stick when neither is.
+    fn axis(
+        &self,
+        positive: GameControl,
+        negative: GameControl,
+        stick_axis: GamepadAxisType,
+        player_number: u8,
+    ) -> f32 {
+        let digital = get_movement(self.pressed(positive, player_number))
+            - get_movement(self.pressed(negative, player_number));
+        if digital != 0. {
+            return digital;
+        }
+        let stick = self
+            .gamepad(player_number)
+            .and_then(|gamepad| self.gamepad_axes.get(GamepadAxis::new(gamepad, stick_axis)))
+            .unwrap_or(0.);
+        if stick.abs() < STICK_DEAD_ZONE {
+            0.
+        } else {
+            stick
         }
     }
 }
 
-pub fn get_movement(control: GameControl, input: &Res<Input<KeyCode>>, player_number: u8) -> f32 {
-    if control.pressed(input, player_number) {
+fn get_movement(pressed: bool) -> f32 {
+    if pressed {
         1.0
     } else {
         0.0
     }
 }
-
-pub fn get_control(control: GameControl, input: &Res<Input<KeyCode>>, player_number: u8) -> bool {
-    control.pressed(input, player_number)
-}
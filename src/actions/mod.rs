@@ -1,19 +1,26 @@
 use bevy::prelude::*;
 
-use crate::actions::game_control::{get_control, get_movement, GameControl};
+use crate::player::MAX_PLAYERS;
 use crate::GameState;
 
+mod ai;
 mod game_control;
 
+pub use ai::Pilot;
+pub use game_control::{GameControl, PlayerInput};
+
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
+// This plugin listens for keyboard and gamepad input and converts the input into Actions
 // Actions can then be used as a resource in other systems to act on the player input.
+// Players flown by the computer get their actions from an AI instead.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Actions>().add_systems(
             Update,
-            set_movement_actions.run_if(in_state(GameState::Playing)),
+            (set_movement_actions, ai::set_ai_actions)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
         );
     }
 }
@@ -22,28 +29,32 @@ impl Plugin for ActionsPlugin {
 pub struct PlayerActions {
     pub rotation: Option<f32>,
     pub thrust: bool,
+    pub reverse: bool,
+    /// Sideways thrust from -1 (left) to 1 (right).
+    pub strafe: f32,
     pub fire: bool,
 }
 
+/// The actions of every player that could be in a match, see [`crate::player::Player::index`].
 #[derive(Default, Resource)]
 pub struct Actions {
-    pub player_actions: [PlayerActions; 2],
+    pub player_actions: [PlayerActions; MAX_PLAYERS],
 }
 
-pub fn set_movement_actions(mut actions: ResMut<Actions>, keyboard_input: Res<Input<KeyCode>>) {
-    for player_number in 0..=1 {
-        let player_rotation = get_movement(GameControl::Left, &keyboard_input, player_number + 1)
-            - get_movement(GameControl::Right, &keyboard_input, player_number + 1);
+pub fn set_movement_actions(mut actions: ResMut<Actions>, input: PlayerInput) {
+    for (index, player_actions) in actions.player_actions.iter_mut().enumerate() {
+        let player_number = index as u8 + 1;
+        let player_rotation = input.rotation(player_number);
 
         if player_rotation != 0. {
-            actions.player_actions[player_number as usize].rotation = Some(player_rotation);
+            player_actions.rotation = Some(player_rotation);
         } else {
-            actions.player_actions[player_number as usize].rotation = None;
+            player_actions.rotation = None;
         }
 
-        actions.player_actions[player_number as usize].thrust =
-            get_control(GameControl::Up, &keyboard_input, player_number + 1);
-        actions.player_actions[player_number as usize].fire =
-            get_control(GameControl::Fire, &keyboard_input, player_number + 1);
+        player_actions.thrust = input.pressed(GameControl::Up, player_number);
+        player_actions.reverse = input.pressed(GameControl::Down, player_number);
+        player_actions.strafe = input.strafe(player_number);
+        player_actions.fire = input.pressed(GameControl::Fire, player_number);
     }
 }
@@ -1,56 +1,207 @@
-use crate::actions::{set_movement_actions, Actions};
+use crate::actions::set_movement_actions;
+use crate::boundaries::Arena;
 use crate::loading::AudioAssets;
-use crate::GameState;
+use crate::physics::{CollisionEvent, ImpactEvent};
+use crate::player::{move_player, Engine, Player, ShipDestroyed, ShotFired};
+use crate::{GameState, Settings};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_kira_audio::prelude::*;
 
 pub struct InternalAudioPlugin;
 
 // This plugin is responsible to control the game audio
+// Music and sound effects play on separate channels so their volumes can be set independently
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(AudioPlugin)
-            .add_systems(OnEnter(GameState::Playing), start_audio);
-        // .add_systems(
-        //     Update,
-        //     control_flying_sound
-        //         .after(set_movement_actions)
-        //         .run_if(in_state(GameState::Playing)),
-        // );
+            .add_audio_channel::<Music>()
+            .add_audio_channel::<Effects>()
+            .init_resource::<ThrustSounds>()
+            .add_systems(OnEnter(GameState::Paused), pause_effects)
+            .add_systems(
+                OnExit(GameState::Paused),
+                resume_effects.run_if(not(in_state(GameState::Settings))),
+            )
+            .add_systems(OnEnter(GameState::Menu), stop_effects)
+            .add_systems(OnEnter(GameState::Results), stop_effects)
+            .add_systems(
+                Update,
+                apply_volume_settings.run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_thrust_sounds,
+                    control_thrust_sounds
+                        .after(set_movement_actions)
+                        .after(move_player),
+                    play_shot_sounds,
+                    play_collision_sounds,
+                    play_explosion_sounds,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
+/// Audio channel for background music.
 #[derive(Resource)]
-struct FlyingAudio(Handle<AudioInstance>);
+pub struct Music;
 
-fn start_audio(mut commands: Commands, audio_assets: Res<AudioAssets>, audio: Res<Audio>) {
-    audio.pause();
-    let handle = audio
-        .play(audio_assets.flying.clone())
-        .looped()
-        .with_volume(0.3)
-        .handle();
-    commands.insert_resource(FlyingAudio(handle));
+/// Audio channel for sound effects.
+#[derive(Resource)]
+pub struct Effects;
+
+/// Thrust loops are quieter than the one-shot effects.
+const THRUST_VOLUME: f64 = 0.3;
+
+/// The looping thrust sound of every ship, paused while the ship isn't thrusting.
+#[derive(Resource, Default)]
+struct ThrustSounds(HashMap<Entity, Handle<AudioInstance>>);
+
+/// Stereo panning for a sound emitted at `position`, from 0 (left) to 1 (right).
+/// Sounds are never panned all the way to one side, so they can still be heard on both speakers.
+fn panning(position: Vec3, arena: &Arena) -> f64 {
+    let relative_x = (position.x / arena.half_size.x).clamp(-1., 1.);
+    0.5 + 0.4 * relative_x as f64
+}
+
+fn pause_effects(effects: Res<AudioChannel<Effects>>) {
+    effects.pause();
+}
+
+fn resume_effects(effects: Res<AudioChannel<Effects>>) {
+    effects.resume();
+}
+
+fn stop_effects(effects: Res<AudioChannel<Effects>>, mut thrust_sounds: ResMut<ThrustSounds>) {
+    effects.stop();
+    thrust_sounds.0.clear();
+}
+
+fn apply_volume_settings(
+    settings: Res<Settings>,
+    effects: Res<AudioChannel<Effects>>,
+    thrust_sounds: Res<ThrustSounds>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    // The music volume is handled by the `MusicPlugin`, together with its fades
+    effects.set_volume(settings.audio.sfx());
+    // Setting the channel volume overrides the volume of the thrust loops
+    for handle in thrust_sounds.0.values() {
+        if let Some(instance) = audio_instances.get_mut(handle) {
+            instance.set_volume(THRUST_VOLUME * settings.audio.sfx(), AudioTween::default());
+        }
+    }
+}
+
+/// Starts a thrust loop for every new ship and stops the ones of ships that are gone.
+fn track_thrust_sounds(
+    mut thrust_sounds: ResMut<ThrustSounds>,
+    new_ships: Query<Entity, Added<Player>>,
+    ships: Query<(), With<Player>>,
+    audio_assets: Res<AudioAssets>,
+    effects: Res<AudioChannel<Effects>>,
+    settings: Res<Settings>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    thrust_sounds.0.retain(|entity, handle| {
+        let alive = ships.contains(*entity);
+        if !alive {
+            if let Some(instance) = audio_instances.get_mut(&*handle) {
+                instance.stop(AudioTween::default());
+            }
+        }
+        alive
+    });
+    for entity in new_ships.iter() {
+        let handle = effects
+            .play(audio_assets.flying.clone())
+            .looped()
+            .paused()
+            .with_volume(THRUST_VOLUME * settings.audio.sfx())
+            .handle();
+        thrust_sounds.0.insert(entity, handle);
+    }
 }
 
-fn control_flying_sound(
-    actions: Res<Actions>,
-    audio: Res<FlyingAudio>,
+fn control_thrust_sounds(
+    thrust_sounds: Res<ThrustSounds>,
+    ships: Query<(&Engine, &Transform)>,
+    arena: Res<Arena>,
     mut audio_instances: ResMut<Assets<AudioInstance>>,
 ) {
-    if let Some(instance) = audio_instances.get_mut(&audio.0) {
+    for (entity, handle) in thrust_sounds.0.iter() {
+        let Ok((engine, transform)) = ships.get(*entity) else {
+            continue;
+        };
+        let Some(instance) = audio_instances.get_mut(handle) else {
+            continue;
+        };
+        instance.set_panning(
+            panning(transform.translation, &arena),
+            AudioTween::default(),
+        );
+        let thrusting = engine.thrusting();
         match instance.state() {
-            PlaybackState::Paused { .. } => {
-                // if actions.player_rotation.is_some() {
-                //     instance.resume(AudioTween::default());
-                // }
+            PlaybackState::Paused { .. } if thrusting => {
+                instance.resume(AudioTween::default());
             }
-            PlaybackState::Playing { .. } => {
-                // if actions.player_rotation.is_none() {
-                //     instance.pause(AudioTween::default());
-                // }
+            PlaybackState::Playing { .. } if !thrusting => {
+                instance.pause(AudioTween::default());
             }
             _ => {}
         }
     }
 }
+
+fn play_shot_sounds(
+    mut shots: EventReader<ShotFired>,
+    audio_assets: Res<AudioAssets>,
+    effects: Res<AudioChannel<Effects>>,
+    arena: Res<Arena>,
+) {
+    for shot in shots.read() {
+        effects
+            .play(audio_assets.shoot.clone())
+            .with_panning(panning(shot.position, &arena));
+    }
+}
+
+/// Bodies resting against each other keep bouncing a tiny bit, softer impacts stay silent.
+const MIN_IMPACT_ENERGY: f32 = 500.;
+
+fn play_collision_sounds(
+    mut collisions: EventReader<CollisionEvent>,
+    mut impacts: EventReader<ImpactEvent>,
+    audio_assets: Res<AudioAssets>,
+    effects: Res<AudioChannel<Effects>>,
+    arena: Res<Arena>,
+) {
+    let positions = collisions.read().map(|collision| collision.position).chain(
+        impacts
+            .read()
+            .filter(|impact| impact.energy >= MIN_IMPACT_ENERGY)
+            .map(|impact| impact.position),
+    );
+    for position in positions {
+        effects
+            .play(audio_assets.impact.clone())
+            .with_panning(panning(position, &arena));
+    }
+}
+
+fn play_explosion_sounds(
+    mut destroyed_ships: EventReader<ShipDestroyed>,
+    audio_assets: Res<AudioAssets>,
+    effects: Res<AudioChannel<Effects>>,
+    arena: Res<Arena>,
+) {
+    for destroyed in destroyed_ships.read() {
+        effects
+            .play(audio_assets.explosion.clone())
+            .with_panning(panning(destroyed.position, &arena));
+    }
+}
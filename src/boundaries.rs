@@ -1,26 +1,95 @@
+use crate::physics::Velocity;
+use crate::{GameState, MatchEntity};
 use bevy::prelude::*;
+use serde::Deserialize;
 
-const BOUNDARY_DIMENSIONS: Vec2 = Vec2::new(725., 350.);
+/// What happens to bodies reaching the edge of the arena.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoundaryMode {
+    /// Bodies leaving through one side come back through the opposite one.
+    #[default]
+    Wrap,
+    /// Bodies bounce off the edges.
+    Bounce,
+}
+
+impl BoundaryMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BoundaryMode::Wrap => "Wrap",
+            BoundaryMode::Bounce => "Bounce",
+        }
+    }
+}
+
+/// The playable area, centered on the origin.
+/// It's set up by the level at the start of every match.
+#[derive(Resource, Clone, Debug)]
+pub struct Arena {
+    pub half_size: Vec2,
+    pub boundary_mode: BoundaryMode,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            half_size: Vec2::new(725., 350.),
+            boundary_mode: BoundaryMode::Wrap,
+        }
+    }
+}
 
 pub struct BoundariesPlugin;
 impl Plugin for BoundariesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, wrap);
+        app.init_resource::<Arena>().add_systems(
+            Update,
+            enforce_boundaries.run_if(in_state(GameState::Playing)),
+        );
     }
 }
 
-fn wrap(mut query: Query<&mut Transform>) {
-    for mut transform in query.iter_mut() {
-        if transform.translation.x > BOUNDARY_DIMENSIONS.x {
-            transform.translation.x = -BOUNDARY_DIMENSIONS.x;
-        } else if transform.translation.x < -BOUNDARY_DIMENSIONS.x {
-            transform.translation.x = BOUNDARY_DIMENSIONS.x;
+fn enforce_boundaries(
+    arena: Res<Arena>,
+    mut query: Query<(&mut Transform, Option<&mut Velocity>), With<MatchEntity>>,
+) {
+    for (mut transform, velocity) in query.iter_mut() {
+        match arena.boundary_mode {
+            BoundaryMode::Wrap => wrap(&arena, &mut transform),
+            BoundaryMode::Bounce => bounce(&arena, &mut transform, velocity),
         }
+    }
+}
+
+fn wrap(arena: &Arena, transform: &mut Transform) {
+    if transform.translation.x > arena.half_size.x {
+        transform.translation.x = -arena.half_size.x;
+    } else if transform.translation.x < -arena.half_size.x {
+        transform.translation.x = arena.half_size.x;
+    }
 
-        if transform.translation.y > BOUNDARY_DIMENSIONS.y {
-            transform.translation.y = -BOUNDARY_DIMENSIONS.y;
-        } else if transform.translation.y < -BOUNDARY_DIMENSIONS.y {
-            transform.translation.y = BOUNDARY_DIMENSIONS.y;
+    if transform.translation.y > arena.half_size.y {
+        transform.translation.y = -arena.half_size.y;
+    } else if transform.translation.y < -arena.half_size.y {
+        transform.translation.y = arena.half_size.y;
+    }
+}
+
+/// Keeps the body inside the arena and reflects its velocity off the edge it reached.
+fn bounce(arena: &Arena, transform: &mut Transform, velocity: Option<Mut<Velocity>>) {
+    let position = transform.translation.truncate();
+    let clamped = position.clamp(-arena.half_size, arena.half_size);
+    if position == clamped {
+        return;
+    }
+    transform.translation = clamped.extend(transform.translation.z);
+    if let Some(mut velocity) = velocity {
+        // Always point back inside, even if the body was already turning around
+        if position.x != clamped.x {
+            velocity.0.x = velocity.0.x.abs() * (clamped.x - position.x).signum();
+        }
+        if position.y != clamped.y {
+            velocity.0.y = velocity.0.y.abs() * (clamped.y - position.y).signum();
         }
     }
 }
@@ -0,0 +1,141 @@
+use crate::boundaries::Arena;
+use crate::player::{Player, ShipDestroyed};
+use crate::{GameState, Settings};
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+
+pub struct CameraPlugin;
+
+/// This plugin owns the camera: it keeps the whole arena in view whatever the window's
+/// aspect ratio, zooms in on the ships if the player wants it to, and shakes on explosions.
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_camera).add_systems(
+            Update,
+            (
+                add_trauma,
+                (frame_ships, shake_camera).run_if(not(in_state(GameState::Loading))),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Room kept around the arena, so ships at its edges are fully visible.
+const ARENA_MARGIN: f32 = 50.;
+/// Room kept around the ships when zooming in on them.
+const SHIP_MARGIN: f32 = 200.;
+/// The camera never zooms in closer than this share of the arena.
+const MIN_ZOOM: f32 = 0.4;
+/// How quickly the camera catches up with the ships, higher is snappier.
+const FOLLOW_SPEED: f32 = 3.;
+/// Trauma added by every destroyed ship, trauma is capped at 1.
+const EXPLOSION_TRAUMA: f32 = 0.6;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.2;
+/// Offset and rotation of the camera at full trauma.
+const MAX_SHAKE_OFFSET: f32 = 30.;
+const MAX_SHAKE_ANGLE: f32 = 0.05;
+
+/// The gameplay camera. The shake is applied on top of the framed area.
+#[derive(Component, Default)]
+pub struct MainCamera {
+    /// Center of the area in view, before shaking.
+    center: Vec2,
+    /// Size of the area in view, the window's aspect ratio may add more around it.
+    size: Vec2,
+    /// Goes from 0 to 1, the shake grows with its square.
+    trauma: f32,
+}
+
+fn spawn_camera(mut commands: Commands, arena: Res<Arena>) {
+    commands.spawn((
+        Camera2dBundle::default(),
+        MainCamera {
+            size: arena_view(&arena),
+            ..default()
+        },
+    ));
+}
+
+fn arena_view(arena: &Arena) -> Vec2 {
+    arena.half_size * 2. + ARENA_MARGIN * 2.
+}
+
+fn add_trauma(
+    mut destroyed_ships: EventReader<ShipDestroyed>,
+    mut cameras: Query<&mut MainCamera>,
+) {
+    let trauma = destroyed_ships.read().count() as f32 * EXPLOSION_TRAUMA;
+    if trauma == 0. {
+        return;
+    }
+    for mut camera in &mut cameras {
+        camera.trauma = (camera.trauma + trauma).min(1.);
+    }
+}
+
+/// Fits the arena in the window, or the area around the ships when zooming is enabled.
+/// Outside of matches there are no ships, so the camera goes back to the whole arena.
+fn frame_ships(
+    mut cameras: Query<(&mut MainCamera, &mut OrthographicProjection)>,
+    ships: Query<&Transform, With<Player>>,
+    arena: Res<Arena>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let arena_size = arena_view(&arena);
+    let (mut target_center, mut target_size) = (Vec2::ZERO, arena_size);
+    if settings.gameplay.camera_zoom {
+        let positions = ships
+            .iter()
+            .map(|transform| transform.translation.truncate());
+        let bounds = positions.fold(None, |bounds: Option<(Vec2, Vec2)>, position| {
+            Some(bounds.map_or((position, position), |(min, max)| {
+                (min.min(position), max.max(position))
+            }))
+        });
+        if let Some((min, max)) = bounds {
+            target_size = (max - min + SHIP_MARGIN * 2.)
+                .max(arena_size * MIN_ZOOM)
+                .min(arena_size);
+            // Don't show anything past the arena's edges
+            let room = (arena_size - target_size) / 2.;
+            target_center = ((min + max) / 2.).clamp(-room, room);
+        }
+    }
+    let follow = 1. - (-FOLLOW_SPEED * time.delta_seconds()).exp();
+    for (mut camera, mut projection) in &mut cameras {
+        camera.center = camera.center.lerp(target_center, follow);
+        camera.size = camera.size.lerp(target_size, follow);
+        projection.scaling_mode = ScalingMode::AutoMin {
+            min_width: camera.size.x,
+            min_height: camera.size.y,
+        };
+    }
+}
+
+fn shake_camera(
+    mut cameras: Query<(&mut MainCamera, &mut Transform)>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let elapsed = time.elapsed_seconds();
+    for (mut camera, mut transform) in &mut cameras {
+        camera.trauma = (camera.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+        let shake = if settings.gameplay.screen_shake {
+            camera.trauma * camera.trauma
+        } else {
+            0.
+        };
+        // Sines with unrelated frequencies, so the shake doesn't look periodic
+        let offset = Vec2::new(
+            (elapsed * 37.).sin() + (elapsed * 23.).sin() * 0.5,
+            (elapsed * 41.).sin() + (elapsed * 29.).sin() * 0.5,
+        ) / 1.5;
+        let angle = (elapsed * 31.).sin() * MAX_SHAKE_ANGLE * shake;
+        transform.translation =
+            (camera.center + offset * MAX_SHAKE_OFFSET * shake).extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
@@ -0,0 +1,209 @@
+//! Command-line options of the native binary.
+
+use star_fighters::{LaunchOptions, MatchConfig};
+
+pub const HELP: &str = "\
+Star Fighters
+
+Usage: star_fighters [OPTIONS]
+
+Options:
+      --level <NAME>          Select a level by file name, like `twin_suns`
+      --config <FILE>         Read the rules of the match from a RON file
+      --seed <N>              Seed the randomness of every match, like the results screen shows
+      --play                  Skip the menu and go straight into a match
+      --fullscreen            Start in fullscreen
+      --windowed              Start in a window
+      --size <WIDTHxHEIGHT>   Size of the window, like `1600x900`
+      --headless              Run without a window or sound, implies --play
+      --ticks <N>             Quit after this many updates, 60 per simulated second when headless
+      --replay <FILE>         Play back a replay, not supported until the game records them
+  -h, --help                  Print this help
+";
+
+/// Everything the command line asks for.
+#[derive(Default)]
+pub struct Cli {
+    pub launch: LaunchOptions,
+    pub match_config: Option<MatchConfig>,
+}
+
+pub enum Parsed {
+    Run(Cli),
+    Help,
+}
+
+impl Cli {
+    /// Parses the arguments, without the name of the binary.
+    /// Values go either after an `=` or in the next argument.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Parsed, String> {
+        let mut cli = Cli::default();
+        let mut seed = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, mut inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("`{flag}` needs a value"))
+            };
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Parsed::Help),
+                "--level" => cli.launch.level = Some(value()?),
+                "--config" => {
+                    let path = value()?;
+                    let config = MatchConfig::load(&path)
+                        .map_err(|error| format!("invalid match config `{path}`: {error}"))?;
+                    cli.match_config = Some(config);
+                }
+                "--seed" => seed = Some(parse_seed(&value()?)?),
+                "--size" => cli.launch.window_size = Some(parse_size(&value()?)?),
+                "--ticks" => cli.launch.tick_limit = Some(parse_ticks(&value()?)?),
+                "--replay" => {
+                    let path = value()?;
+                    return Err(format!(
+                        "can't play back `{path}`, the game doesn't record replays yet"
+                    ));
+                }
+                "--play" | "--fullscreen" | "--windowed" | "--headless" => {
+                    if inline_value.is_some() {
+                        return Err(format!("`{flag}` doesn't take a value"));
+                    }
+                    match flag.as_str() {
+                        "--play" => cli.launch.skip_menu = true,
+                        "--headless" => cli.launch.headless = true,
+                        fullscreen => {
+                            let fullscreen = fullscreen == "--fullscreen";
+                            if cli
+                                .launch
+                                .fullscreen
+                                .is_some_and(|other| other != fullscreen)
+                            {
+                                return Err(
+                                    "`--fullscreen` and `--windowed` can't be used together"
+                                        .to_string(),
+                                );
+                            }
+                            cli.launch.fullscreen = Some(fullscreen);
+                        }
+                    }
+                }
+                _ => return Err(format!("unknown argument `{flag}`")),
+            }
+        }
+        // Applied last, so it also overrides the seed of a config file given after it
+        if let Some(seed) = seed {
+            cli.match_config
+                .get_or_insert_with(MatchConfig::default)
+                .seed = Some(seed);
+        }
+        if cli.launch.headless {
+            if cli.launch.fullscreen.is_some() || cli.launch.window_size.is_some() {
+                return Err("window options can't be used with `--headless`".to_string());
+            }
+            cli.launch.skip_menu = true;
+        }
+        Ok(Parsed::Run(cli))
+    }
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let invalid =
+        || format!("invalid window size `{value}`, expected WIDTHxHEIGHT like `1600x900`");
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok((width, height))
+}
+
+fn parse_seed(value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| {
+        format!("invalid seed `{value}`, expected a whole number like the results screen shows")
+    })
+}
+
+fn parse_ticks(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(ticks) if ticks > 0 => Ok(ticks),
+        _ => Err(format!(
+            "invalid number of ticks `{value}`, expected a positive whole number"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Parsed, String> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_error(args: &[&str]) -> String {
+        parse(args).err().expect("the arguments should be rejected")
+    }
+
+    #[test]
+    fn rejects_unknown_flags() {
+        assert_eq!(
+            parse_error(&["--levle", "twin_suns"]),
+            "unknown argument `--levle`"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        assert_eq!(parse_error(&["--level"]), "`--level` needs a value");
+        assert_eq!(
+            parse_error(&["--play", "--ticks"]),
+            "`--ticks` needs a value"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_sizes() {
+        for size in ["1600", "1600x", "x900", "1600x0", "wide", "1600*900"] {
+            assert!(
+                parse_error(&["--size", size]).starts_with("invalid window size"),
+                "`{size}` should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_non_numeric_seeds() {
+        for seed in ["lucky", "-1", "4.2"] {
+            assert!(
+                parse_error(&["--seed", seed]).starts_with("invalid seed"),
+                "`{seed}` should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_ticks_without_headless() {
+        let Ok(Parsed::Run(cli)) = parse(&["--ticks=600"]) else {
+            panic!("`--ticks` should work with a window too");
+        };
+        assert_eq!(cli.launch.tick_limit, Some(600));
+        assert!(!cli.launch.headless);
+        assert!(!cli.launch.skip_menu);
+    }
+
+    #[test]
+    fn rejects_replays() {
+        assert_eq!(
+            parse_error(&["--replay", "duel.replay"]),
+            "can't play back `duel.replay`, the game doesn't record replays yet"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::camera::MainCamera;
+use crate::physics::{apply_forces, Acceleration, Collider, ForceSource, Forces, Mass, Velocity};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+pub struct DebugPlugin;
+
+/// This plugin draws what the physics are doing, to help tuning them.
+/// [`TOGGLE_KEY`] shows or hides the overlay during a match, and clicking a body
+/// shows its mass, speed, net force and torque.
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .add_systems(Startup, spawn_readout)
+            .add_systems(
+                Update,
+                (
+                    toggle_overlay,
+                    (select_body, draw_physics.after(apply_forces)).run_if(overlay_enabled),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+            )
+            .add_systems(Update, update_readout.after(apply_forces));
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+const COLLIDER_COLOR: Color = Color::rgb(0.2, 1., 0.2);
+const VELOCITY_COLOR: Color = Color::WHITE;
+const ACCELERATION_COLOR: Color = Color::rgb(1., 0.2, 1.);
+const SELECTION_COLOR: Color = Color::YELLOW;
+/// Vectors are drawn as far as the body would go in this many seconds.
+const VELOCITY_SCALE: f32 = 0.5;
+const ACCELERATION_SCALE: f32 = 0.5;
+/// Forces are drawn this many pixels long per unit.
+const FORCE_SCALE: f32 = 0.5;
+const ARROW_HEAD_SIZE: f32 = 8.;
+
+#[derive(Resource, Default)]
+struct DebugOverlay {
+    enabled: bool,
+    /// The body whose values are shown in the readout.
+    selected: Option<Entity>,
+}
+
+#[derive(Component)]
+struct DebugReadout;
+
+fn overlay_enabled(overlay: Res<DebugOverlay>) -> bool {
+    overlay.enabled
+}
+
+fn toggle_overlay(mut overlay: ResMut<DebugOverlay>, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+/// Selects the body under the cursor when clicking, or nothing if there's none.
+fn select_body(
+    mut overlay: ResMut<DebugOverlay>,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    bodies: Query<(Entity, &Transform, &Collider), With<Mass>>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+    overlay.selected = bodies
+        .iter()
+        .find(|(_, transform, collider)| {
+            (cursor - transform.translation.truncate())
+                .abs()
+                .cmple(collider.dimensions / 2.)
+                .all()
+        })
+        .map(|(entity, _, _)| entity);
+}
+
+fn force_color(source: ForceSource) -> Color {
+    match source {
+        ForceSource::Gravity => Color::rgb(0.3, 0.5, 1.),
+        ForceSource::Thrust => Color::rgb(1., 0.6, 0.1),
+        ForceSource::ReverseThrust => Color::rgb(1., 0.3, 0.1),
+        ForceSource::Strafe => Color::rgb(1., 0.8, 0.5),
+        ForceSource::FlightAssist => Color::rgb(0.6, 0.4, 1.),
+        ForceSource::RotationDamping => Color::rgb(0.8, 0.6, 1.),
+        ForceSource::Shockwave => Color::rgb(0.5, 0.9, 1.),
+        ForceSource::SolarWind => Color::rgb(1., 1., 0.4),
+    }
+}
+
+fn draw_arrow(gizmos: &mut Gizmos, start: Vec2, vector: Vec2, color: Color) {
+    if vector.length_squared() < 1. {
+        return;
+    }
+    let end = start + vector;
+    let back = -vector.normalize() * ARROW_HEAD_SIZE;
+    gizmos.line_2d(start, end, color);
+    gizmos.line_2d(end, end + back.rotate(Vec2::from_angle(0.5)), color);
+    gizmos.line_2d(end, end + back.rotate(Vec2::from_angle(-0.5)), color);
+}
+
+fn draw_physics(
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+    colliders: Query<(Entity, &Transform, &Collider)>,
+    bodies: Query<(&Transform, &Velocity, &Acceleration, &Forces)>,
+) {
+    for (entity, transform, collider) in &colliders {
+        let color = if overlay.selected == Some(entity) {
+            SELECTION_COLOR
+        } else {
+            COLLIDER_COLOR
+        };
+        // Collisions ignore rotation, so colliders are drawn without it
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            0.,
+            collider.dimensions,
+            color,
+        );
+    }
+    for (transform, velocity, acceleration, forces) in &bodies {
+        let position = transform.translation.truncate();
+        for (source, force) in forces.iter() {
+            draw_arrow(
+                &mut gizmos,
+                position,
+                force.truncate() * FORCE_SCALE,
+                force_color(source),
+            );
+        }
+        draw_arrow(
+            &mut gizmos,
+            position,
+            acceleration.0.truncate() * ACCELERATION_SCALE,
+            ACCELERATION_COLOR,
+        );
+        draw_arrow(
+            &mut gizmos,
+            position,
+            velocity.0.truncate() * VELOCITY_SCALE,
+            VELOCITY_COLOR,
+        );
+    }
+}
+
+fn spawn_readout(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            String::new(),
+            TextStyle {
+                font_size: 20.,
+                color: SELECTION_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(10.),
+            ..default()
+        }),
+        DebugReadout,
+    ));
+}
+
+/// Shows the values of the selected body, as long as the overlay is enabled and it still exists.
+fn update_readout(
+    overlay: Res<DebugOverlay>,
+    bodies: Query<(&Mass, Option<&Velocity>, Option<&Forces>)>,
+    mut readouts: Query<(&mut Text, &mut Visibility), With<DebugReadout>>,
+) {
+    let selected = overlay
+        .selected
+        .filter(|_| overlay.enabled)
+        .and_then(|entity| bodies.get(entity).ok());
+    for (mut text, mut visibility) in &mut readouts {
+        let Some((mass, velocity, forces)) = selected else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let speed = velocity.map_or(0., |velocity| velocity.0.length());
+        let net_force = forces.map_or(Vec3::ZERO, Forces::net);
+        let torque = forces.map_or(0., Forces::torque);
+        text.sections[0].value = format!(
+            "Mass: {:.2}  Speed: {speed:.1}  Net force: {:.1} ({:.1}, {:.1})  Torque: {torque:.1}",
+            mass.0,
+            net_force.length(),
+            net_force.x,
+            net_force.y
+        );
+        *visibility = Visibility::Inherited;
+    }
+}
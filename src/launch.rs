@@ -0,0 +1,94 @@
+use crate::level::{Level, SelectedLevel};
+use crate::loading::LevelAssets;
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+pub struct LaunchPlugin;
+
+/// This plugin carries out the [`LaunchOptions`] the game was started with:
+/// it selects the requested level once everything is loaded, skips the menu
+/// and quits after the tick limit, or as soon as loading fails when running headless.
+impl Plugin for LaunchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LaunchOptions>()
+            .add_systems(OnEnter(GameState::Menu), apply_launch_options)
+            .add_systems(
+                OnEnter(GameState::LoadingFailed),
+                quit.run_if(|options: Res<LaunchOptions>| options.headless),
+            )
+            .add_systems(
+                Update,
+                count_ticks.run_if(|options: Res<LaunchOptions>| options.tick_limit.is_some()),
+            );
+    }
+}
+
+/// How the game was started, the native binary fills these in from the command line.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct LaunchOptions {
+    /// File name of the level to select, without the `.level.ron` extension.
+    pub level: Option<String>,
+    /// Go straight into a match instead of stopping at the menu.
+    pub skip_menu: bool,
+    /// Quit after this many updates.
+    pub tick_limit: Option<u32>,
+    /// Running without a window, nobody would see the loading errors.
+    pub headless: bool,
+    /// Overrides the video settings until they're changed, without being saved.
+    pub fullscreen: Option<bool>,
+    /// Overrides the resolution of the video settings until they're changed,
+    /// without being saved.
+    pub window_size: Option<(u32, u32)>,
+}
+
+const LEVEL_EXTENSION: &str = ".level.ron";
+
+/// Only applies once, coming back to the menu later on works as usual.
+fn apply_launch_options(
+    mut options: ResMut<LaunchOptions>,
+    mut selected_level: ResMut<SelectedLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+    level_assets: Res<LevelAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(name) = options.level.take() {
+        let level_name = |handle: &Handle<Level>| {
+            asset_server.get_path(handle).and_then(|path| {
+                let file_name = path.path().file_name()?.to_str()?;
+                file_name.strip_suffix(LEVEL_EXTENSION).map(str::to_owned)
+            })
+        };
+        let level = level_assets
+            .levels
+            .iter()
+            .find(|level| level_name(level).as_ref() == Some(&name));
+        let Some(level) = level else {
+            let available: Vec<String> =
+                level_assets.levels.iter().filter_map(level_name).collect();
+            error!(
+                "Unknown level `{name}`, the available levels are: {}",
+                available.join(", ")
+            );
+            exit.send(AppExit);
+            return;
+        };
+        selected_level.0 = level.clone();
+    }
+    if options.skip_menu {
+        options.skip_menu = false;
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn quit(mut exit: EventWriter<AppExit>) {
+    exit.send(AppExit);
+}
+
+fn count_ticks(mut ticks: Local<u32>, options: Res<LaunchOptions>, mut exit: EventWriter<AppExit>) {
+    *ticks += 1;
+    if options.tick_limit.is_some_and(|limit| *ticks >= limit) {
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,603 @@
+use crate::boundaries::{Arena, BoundaryMode};
+use crate::loading::{LevelAssets, RonAsset, RonAssetLoader, TextureAssets};
+use crate::match_setup::MatchConfig;
+use crate::physics::portals::Portal;
+use crate::physics::sources::{EventHorizon, Pulsar, SolarWind};
+use crate::physics::{
+    circular_orbit_speed, gravitational_force, Collider, Mass, PhysicsBundle, Star, Velocity,
+};
+use crate::{GameState, MatchEntity, RestartMatch, SpawnMatch};
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use rand::Rng;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+pub struct LevelPlugin;
+
+/// This plugin loads levels from `.level.ron` files and spawns the selected one
+/// at the start of every match.
+/// With the `dev` feature, saving a level file restarts the running match with the new layout.
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>()
+            .init_asset_loader::<RonAssetLoader<Level>>()
+            .add_systems(
+                OnExit(GameState::Loading),
+                select_first_level.run_if(resource_exists::<LevelAssets>()),
+            )
+            .add_systems(SpawnMatch, spawn_level)
+            .add_systems(Update, reload_modified_level);
+    }
+}
+
+/// Everything about the playing field of a match.
+/// See `assets/levels` for examples.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct Level {
+    pub name: String,
+    /// Full width and height of the arena, centered on the origin.
+    pub arena_size: Vec2,
+    #[serde(default)]
+    pub boundary_mode: BoundaryMode,
+    #[serde(default)]
+    pub stars: Vec<StarDefinition>,
+    /// Where ships start, in player order, unless the [`MatchConfig`] asks for random spawns.
+    /// Matches with more players than spawn points spread the ships around the first star instead.
+    #[serde(default)]
+    pub spawn_points: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    pub wormholes: Vec<Wormhole>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StarDefinition {
+    #[serde(default)]
+    pub kind: StarKind,
+    pub position: Vec2,
+    /// Negative for repulsors.
+    pub mass: f32,
+    /// Diameter of the star's sprite.
+    pub size: f32,
+    /// Repulsors don't have one.
+    #[serde(default)]
+    pub collider: Vec2,
+    /// Dynamic stars move: other stars pull them, as does everything they pull,
+    /// and they take the momentum of whatever crashes into them.
+    #[serde(default)]
+    pub dynamic: bool,
+    /// Initial velocity of a dynamic star.
+    #[serde(default)]
+    pub velocity: Vec2,
+    /// How hard the star pushes ships in plain view away, 0 for none.
+    #[serde(default)]
+    pub solar_wind: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+pub enum StarKind {
+    #[default]
+    Star,
+    /// Swallows anything destroyable closer than `event_horizon`.
+    BlackHole { event_horizon: f32 },
+    /// Sends out a shockwave every `period` seconds, pushing things away with `strength`.
+    Pulsar { period: f32, strength: f32 },
+    /// A nebula with a negative mass, pushing things away. Ships fly right through it.
+    Repulsor,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnPoint {
+    pub position: Vec2,
+    /// Ships spawned without one orbit the stars, see [`Level::orbital_velocity`].
+    #[serde(default)]
+    pub velocity: Option<Vec2>,
+    /// Counterclockwise rotation in radians, ships point up by default.
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+/// Solid, indestructible blocks.
+#[derive(Deserialize, Debug)]
+pub struct Obstacle {
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Ships with a restitution too bounce off it instead of crashing, see [`Collider`].
+    #[serde(default)]
+    pub restitution: Option<f32>,
+}
+
+/// A pair of portals, anything crossing one of them comes out of the other.
+#[derive(Deserialize, Debug)]
+pub struct Wormhole {
+    pub ends: (Vec2, Vec2),
+    pub radius: f32,
+    /// Radians bodies are turned by when going from the first end to the second one,
+    /// and back when going the other way.
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+/// The level the next match is played on.
+#[derive(Resource)]
+pub struct SelectedLevel(pub Handle<Level>);
+
+impl SelectedLevel {
+    /// Selects the level after the current one, going back to the first one after the last.
+    pub fn select_next(&mut self, level_assets: &LevelAssets) {
+        let current = level_assets
+            .levels
+            .iter()
+            .position(|level| *level == self.0);
+        let next = current.map_or(0, |index| (index + 1) % level_assets.levels.len());
+        self.0 = level_assets.levels[next].clone();
+    }
+}
+
+/// Both ends of a wormhole share a color, cycling through these.
+const WORMHOLE_COLORS: [Color; 3] = [
+    Color::rgba(0.2, 0.9, 0.6, 0.35),
+    Color::rgba(1., 0.5, 0.2, 0.35),
+    Color::rgba(0.9, 0.3, 0.7, 0.35),
+];
+const PULSAR_COLOR: Color = Color::rgb(0.6, 0.9, 1.);
+const BLACK_HOLE_COLOR: Color = Color::rgb(0.08, 0.02, 0.12);
+const REPULSOR_COLOR: Color = Color::rgba(0.6, 0.3, 0.9, 0.3);
+
+/// Generated spawn points are this far from the star, relative to the size of the arena.
+const SPAWN_RING_SCALE: f32 = 0.8;
+/// Random spawn points keep at least this much room around the surface of stars.
+const SPAWN_STAR_CLEARANCE: f32 = 150.;
+/// Random spawn points are at least this far from each other, and from ships already flying.
+const SPAWN_OPPONENT_CLEARANCE: f32 = 400.;
+/// Random spawn points keep at least this much room around obstacles, portals
+/// and the edges of the arena.
+const SPAWN_CLEARANCE: f32 = 60.;
+/// Random spots tried for every ship before settling for the safest one.
+const SPAWN_ATTEMPTS: usize = 100;
+
+/// Where ships start a match.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpawnPlacement {
+    /// The level's own spawn points.
+    #[default]
+    Level,
+    /// Random spots, clear of stars, obstacles and the other ships.
+    Random,
+}
+
+impl SpawnPlacement {
+    pub fn cycle(&mut self) {
+        *self = match self {
+            SpawnPlacement::Level => SpawnPlacement::Random,
+            SpawnPlacement::Random => SpawnPlacement::Level,
+        };
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpawnPlacement::Level => "Level",
+            SpawnPlacement::Random => "Random",
+        }
+    }
+}
+
+impl RonAsset for Level {
+    const EXTENSIONS: &'static [&'static str] = &["level.ron"];
+
+    fn validate(&self) -> Result<(), String> {
+        if self.arena_size.x <= 0. || self.arena_size.y <= 0. {
+            return Err(format!(
+                "arena_size must be positive, got {}",
+                self.arena_size
+            ));
+        }
+        let half_size = self.arena_size / 2.;
+        for (index, spawn_point) in self.spawn_points.iter().enumerate() {
+            if spawn_point.position.abs().cmpgt(half_size).any() {
+                return Err(format!(
+                    "spawn point {index} at {} is outside of the arena",
+                    spawn_point.position
+                ));
+            }
+        }
+        for (index, star) in self.stars.iter().enumerate() {
+            if star.size <= 0. {
+                return Err(format!("star {index} must have a positive size"));
+            }
+            match star.kind {
+                StarKind::Repulsor if star.mass >= 0. => {
+                    return Err(format!("repulsor {index} must have a negative mass"));
+                }
+                StarKind::Repulsor if star.dynamic => {
+                    return Err(format!("repulsor {index} can't be dynamic"));
+                }
+                StarKind::Repulsor if index == 0 => {
+                    return Err("the first star can't be a repulsor, ships orbit it".to_string());
+                }
+                StarKind::Repulsor => {}
+                _ if star.mass <= 0. => {
+                    return Err(format!("star {index} must have a positive mass"));
+                }
+                StarKind::BlackHole { event_horizon } if event_horizon <= 0. => {
+                    return Err(format!(
+                        "black hole {index} must have a positive event_horizon"
+                    ));
+                }
+                StarKind::Pulsar { period, .. } if period <= 0. => {
+                    return Err(format!("pulsar {index} must have a positive period"));
+                }
+                _ => {}
+            }
+            if !star.dynamic && star.velocity != Vec2::ZERO {
+                return Err(format!(
+                    "star {index} has a velocity but isn't dynamic, so it wouldn't move"
+                ));
+            }
+        }
+        for (index, wormhole) in self.wormholes.iter().enumerate() {
+            let (first, second) = wormhole.ends;
+            if wormhole.radius <= 0. {
+                return Err(format!("wormhole {index} must have a positive radius"));
+            }
+            if first.abs().cmpgt(half_size).any() || second.abs().cmpgt(half_size).any() {
+                return Err(format!("wormhole {index} has an end outside of the arena"));
+            }
+            if first.distance(second) <= wormhole.radius * 2. {
+                return Err(format!("the ends of wormhole {index} overlap"));
+            }
+        }
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            if let Some(restitution) = obstacle.restitution {
+                if !(0. ..=1.).contains(&restitution) {
+                    return Err(format!(
+                        "obstacle {index} must have a restitution between 0 and 1, got {restitution}"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Level {
+    pub fn arena(&self) -> Arena {
+        Arena {
+            half_size: self.arena_size / 2.,
+            boundary_mode: self.boundary_mode,
+        }
+    }
+
+    /// A counterclockwise circular orbit around the first star, pointing along the orbit,
+    /// with the star's pull scaled by `gravity`.
+    /// Without stars, the body just sits on the circle.
+    pub fn orbit(&self, angle: f32, distance: f32, mass: f32, gravity: f32) -> SpawnPoint {
+        let (center, speed) = self.stars.first().map_or((Vec2::ZERO, 0.), |star| {
+            (
+                star.position,
+                circular_orbit_speed(star.mass * gravity, mass, distance),
+            )
+        });
+        let direction = Vec2::from_angle(angle);
+        SpawnPoint {
+            position: center + direction * distance,
+            velocity: Some(direction.perp() * speed),
+            rotation: angle,
+        }
+    }
+
+    /// Center of mass of the stars that pull, ships orbit around it.
+    fn orbit_center(&self) -> Vec2 {
+        let (weighted, total) = self
+            .stars
+            .iter()
+            .filter(|star| star.mass > 0.)
+            .fold((Vec2::ZERO, 0.), |(weighted, total), star| {
+                (weighted + star.position * star.mass, total + star.mass)
+            });
+        if total > 0. {
+            weighted / total
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    /// Velocity a body of `mass` at `position` needs for a counterclockwise circular orbit
+    /// around the center of mass of the stars. The pull of every star is worked out like
+    /// in `apply_gravity`, scaled by `gravity`, so a lone star gives a perfect orbit.
+    /// Spots not pulled towards the center, as in levels without stars, get no velocity.
+    pub fn orbital_velocity(&self, position: Vec2, mass: f32, gravity: f32) -> Vec2 {
+        let offset = position - self.orbit_center();
+        let distance = offset.length();
+        if distance == 0. {
+            return Vec2::ZERO;
+        }
+        let pull: Vec3 = self
+            .stars
+            .iter()
+            .map(|star| {
+                gravitational_force(
+                    star.mass * gravity,
+                    mass,
+                    (star.position - position).extend(0.),
+                )
+            })
+            .sum();
+        // Only the part of the pull towards the center keeps the body turning around it
+        let centripetal = -pull.truncate().dot(offset / distance);
+        if centripetal <= 0. {
+            return Vec2::ZERO;
+        }
+        // The pull has to provide the centripetal force: F = m * v^2 / r
+        offset.perp() / distance * (centripetal * distance / mass).sqrt()
+    }
+
+    /// The velocity a ship of `mass` starts with at `spawn_point`.
+    pub fn spawn_velocity(&self, spawn_point: &SpawnPoint, mass: f32, gravity: f32) -> Vec2 {
+        spawn_point
+            .velocity
+            .unwrap_or_else(|| self.orbital_velocity(spawn_point.position, mass, gravity))
+    }
+
+    /// The widest orbit around the first star that stays inside the arena.
+    pub fn max_orbit_distance(&self) -> f32 {
+        let center = self.stars.first().map_or(Vec2::ZERO, |star| star.position);
+        (self.arena_size / 2. - center.abs()).min_element()
+    }
+
+    /// Where the ships of a match with `players` players start.
+    /// Uses the level's own spawn points if there are enough of them, otherwise the ships
+    /// are spread evenly on a ring around the first star, pointing along the ring.
+    pub fn spawn_points(&self, players: usize) -> Vec<SpawnPoint> {
+        if self.spawn_points.len() >= players {
+            return self.spawn_points[..players].to_vec();
+        }
+        let half_size = self.arena_size / 2.;
+        let center = self.stars.first().map_or(Vec2::ZERO, |star| star.position);
+        (0..players)
+            .map(|index| {
+                // The first ship starts on the left, like in the classic level
+                let angle =
+                    std::f32::consts::PI + std::f32::consts::TAU * index as f32 / players as f32;
+                let offset = Vec2::from_angle(angle) * half_size * SPAWN_RING_SCALE;
+                SpawnPoint {
+                    position: (center + offset).clamp(-half_size, half_size),
+                    velocity: None,
+                    rotation: angle,
+                }
+            })
+            .collect()
+    }
+
+    /// Random spots for the ships of a match with `players` players,
+    /// each one picked by [`Level::random_spawn_point`] away from the ones before it.
+    pub fn random_spawn_points(&self, players: usize, rng: &mut impl Rng) -> Vec<SpawnPoint> {
+        let mut taken: Vec<Vec2> = Vec::with_capacity(players);
+        (0..players)
+            .map(|_| {
+                let spawn_point = self.random_spawn_point(&taken, rng);
+                taken.push(spawn_point.position);
+                spawn_point
+            })
+            .collect()
+    }
+
+    /// A random spot close enough to the stars to orbit them without leaving the arena,
+    /// and clear of stars, obstacles, portals and the ships at `opponents`.
+    /// When no spot is clear after [`SPAWN_ATTEMPTS`] tries, the one with the most room is used.
+    /// Ships point along their orbit.
+    pub fn random_spawn_point(&self, opponents: &[Vec2], rng: &mut impl Rng) -> SpawnPoint {
+        let center = self.orbit_center();
+        let max_distance =
+            ((self.arena_size / 2. - center.abs()).min_element() - SPAWN_CLEARANCE).max(0.);
+        let mut best: Option<(Vec2, f32)> = None;
+        for _ in 0..SPAWN_ATTEMPTS {
+            // The square root spreads the spots evenly over the disc
+            let distance = max_distance * rng.gen::<f32>().sqrt();
+            let position = center + Vec2::from_angle(rng.gen_range(0. ..TAU)) * distance;
+            let room = self.room_around(position, opponents);
+            if best.is_none_or(|(_, best_room)| room > best_room) {
+                best = Some((position, room));
+            }
+            if room >= 0. {
+                break;
+            }
+        }
+        let position = best.map_or(center, |(position, _)| position);
+        let offset = position - center;
+        SpawnPoint {
+            position,
+            velocity: None,
+            rotation: offset.y.atan2(offset.x),
+        }
+    }
+
+    /// How far `position` is from breaking the closest of the spawn clearances,
+    /// negative if it's already too close to something.
+    fn room_around(&self, position: Vec2, opponents: &[Vec2]) -> f32 {
+        let stars = self
+            .stars
+            .iter()
+            // Ships fly right through repulsors
+            .filter(|star| !matches!(star.kind, StarKind::Repulsor))
+            .map(|star| {
+                let radius = match star.kind {
+                    StarKind::BlackHole { event_horizon } => event_horizon.max(star.size / 2.),
+                    _ => star.size / 2.,
+                };
+                position.distance(star.position) - radius - SPAWN_STAR_CLEARANCE
+            });
+        let ships = opponents
+            .iter()
+            .map(|opponent| position.distance(*opponent) - SPAWN_OPPONENT_CLEARANCE);
+        let obstacles = self.obstacles.iter().map(|obstacle| {
+            let outside = (position - obstacle.position).abs() - obstacle.size / 2.;
+            outside.max(Vec2::ZERO).length() - SPAWN_CLEARANCE
+        });
+        let portals = self.wormholes.iter().flat_map(|wormhole| {
+            [wormhole.ends.0, wormhole.ends.1]
+                .map(|end| position.distance(end) - wormhole.radius - SPAWN_CLEARANCE)
+        });
+        stars
+            .chain(ships)
+            .chain(obstacles)
+            .chain(portals)
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+fn select_first_level(mut commands: Commands, level_assets: Res<LevelAssets>) {
+    commands.insert_resource(SelectedLevel(level_assets.levels[0].clone()));
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    textures: Res<TextureAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<MatchConfig>,
+) {
+    let level = levels
+        .get(&selected_level.0)
+        .expect("Levels are loaded before leaving `GameState::Loading`");
+    let mut arena = level.arena();
+    if let Some(boundary_mode) = config.boundary_mode {
+        arena.boundary_mode = boundary_mode;
+    }
+    commands.insert_resource(arena);
+    for star in &level.stars {
+        let transform = Transform::from_translation(star.position.extend(0.));
+        let mut entity = commands.spawn((Mass(star.mass), Star, MatchEntity));
+        match star.kind {
+            StarKind::Star | StarKind::Pulsar { .. } => {
+                entity.insert(SpriteBundle {
+                    texture: textures.bevy.clone(),
+                    sprite: Sprite {
+                        color: match star.kind {
+                            StarKind::Pulsar { .. } => PULSAR_COLOR,
+                            _ => Color::WHITE,
+                        },
+                        custom_size: Some(Vec2::splat(star.size)),
+                        ..default()
+                    },
+                    transform,
+                    ..default()
+                });
+            }
+            StarKind::BlackHole { .. } | StarKind::Repulsor => {
+                let (color, transform) = match star.kind {
+                    StarKind::Repulsor => (
+                        REPULSOR_COLOR,
+                        transform.with_translation(star.position.extend(-1.)),
+                    ),
+                    _ => (BLACK_HOLE_COLOR, transform),
+                };
+                entity.insert(MaterialMesh2dBundle {
+                    mesh: meshes.add(shape::Circle::new(star.size / 2.).into()).into(),
+                    material: materials.add(color.into()),
+                    transform,
+                    ..default()
+                });
+            }
+        }
+        match star.kind {
+            StarKind::BlackHole { event_horizon } => {
+                entity.insert(EventHorizon(event_horizon));
+            }
+            StarKind::Pulsar { period, strength } => {
+                entity.insert(Pulsar::new(period, strength));
+            }
+            _ => {}
+        }
+        // Ships fly through repulsor nebulae
+        if !matches!(star.kind, StarKind::Repulsor) {
+            entity.insert(Collider {
+                dimensions: star.collider,
+                destroyable: false, // Should never destroy a star
+                restitution: None,  // Ships crash into stars, they never bounce
+            });
+        }
+        if star.solar_wind > 0. {
+            entity.insert(SolarWind(star.solar_wind));
+        }
+        if star.dynamic {
+            entity.insert(PhysicsBundle {
+                mass: Mass(star.mass),
+                velocity: Velocity(star.velocity.extend(0.)),
+                ..default()
+            });
+        }
+    }
+    for (index, wormhole) in level.wormholes.iter().enumerate() {
+        let color = WORMHOLE_COLORS[index % WORMHOLE_COLORS.len()];
+        let (first, second) = wormhole.ends;
+        let [first, second] = [first, second].map(|position| {
+            commands
+                .spawn((
+                    MaterialMesh2dBundle {
+                        mesh: meshes
+                            .add(shape::Circle::new(wormhole.radius).into())
+                            .into(),
+                        material: materials.add(color.into()),
+                        // Behind the ships
+                        transform: Transform::from_translation(position.extend(-1.)),
+                        ..default()
+                    },
+                    MatchEntity,
+                ))
+                .id()
+        });
+        for (portal, partner, rotation) in [
+            (first, second, wormhole.rotation),
+            (second, first, -wormhole.rotation),
+        ] {
+            commands.entity(portal).insert(Portal {
+                partner,
+                radius: wormhole.radius,
+                rotation,
+            });
+        }
+    }
+    for obstacle in &level.obstacles {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.25, 0.25, 0.3),
+                    custom_size: Some(obstacle.size),
+                    ..default()
+                },
+                transform: Transform::from_translation(obstacle.position.extend(0.)),
+                ..default()
+            },
+            Collider {
+                dimensions: obstacle.size,
+                destroyable: false,
+                restitution: obstacle.restitution,
+            },
+            MatchEntity,
+        ));
+    }
+}
+
+/// Restarts the match when the level being played changes on disk.
+fn reload_modified_level(
+    mut asset_events: EventReader<AssetEvent<Level>>,
+    mut restart: EventWriter<RestartMatch>,
+    selected_level: Option<Res<SelectedLevel>>,
+    match_entities: Query<(), With<MatchEntity>>,
+) {
+    let Some(selected_level) = selected_level else {
+        return;
+    };
+    let modified = asset_events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { id } if *id == selected_level.0.id()));
+    if modified && !match_entities.is_empty() {
+        info!("Level changed on disk, restarting the match");
+        restart.send(RestartMatch);
+    }
+}
@@ -3,24 +3,61 @@
 mod actions;
 mod audio;
 mod boundaries;
+mod camera;
+mod debug;
+mod launch;
+mod level;
 mod loading;
+mod match_setup;
 mod menu;
-mod physics;
+mod modes;
+mod music;
+mod pause;
+pub mod physics;
 mod player;
+mod powerups;
+mod results;
+mod rng;
+mod settings;
+mod ship;
+mod ship_select;
+mod teams;
+mod trajectories;
 
 use actions::ActionsPlugin;
 use audio::InternalAudioPlugin;
 use boundaries::BoundariesPlugin;
+use camera::CameraPlugin;
+use debug::DebugPlugin;
+use launch::LaunchPlugin;
+use level::LevelPlugin;
 use loading::LoadingPlugin;
+use match_setup::MatchSetupPlugin;
 use menu::MenuPlugin;
+use modes::ModesPlugin;
+use music::MusicPlugin;
+use pause::PausePlugin;
 use physics::PhysicsPlugin;
 use player::PlayerPlugin;
+use powerups::PowerUpsPlugin;
+use results::ResultsPlugin;
+use rng::RngPlugin;
+use settings::SettingsPlugin;
+use ship::ShipPlugin;
+use ship_select::ShipSelectPlugin;
+use trajectories::TrajectoriesPlugin;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
+use bevy::ecs::schedule::ScheduleLabel;
 use bevy::prelude::*;
 
+pub use launch::LaunchOptions;
+pub use match_setup::MatchConfig;
+pub use rng::{GameRng, RngStream};
+pub use settings::Settings;
+
 // This example game uses States to separate logic
 // See https://bevy-cheatbook.github.io/programming/states.html
 // Or https://github.com/bevyengine/bevy/blob/main/examples/ecs/state.rs
@@ -33,21 +70,74 @@ enum GameState {
     Playing,
     // Here the menu is drawn and waiting for player interaction
     Menu,
+    // The match is frozen and the pause menu is drawn on top of it
+    Paused,
+    // The settings menu is drawn, opened either from the menu or while paused
+    Settings,
+    // The rules of the next match are picked
+    MatchSetup,
+    // Every player picks a ship class before the match starts
+    ShipSelect,
+    // Some game data couldn't be loaded, the errors are shown instead of the menu
+    LoadingFailed,
+    // A player won the match, the final scores are drawn on top of it
+    Results,
 }
 
+/// Marks everything that belongs to a match (ships, projectiles, stars...).
+/// All of these get despawned when the match is left or restarted.
+#[derive(Component)]
+pub struct MatchEntity;
+
+/// Schedule with the systems that set up a new match.
+/// It runs on `OnEnter(GameState::Playing)` unless a match is already in progress,
+/// so resuming from `GameState::Paused` doesn't spawn everything again,
+/// and whenever a [`RestartMatch`] event is sent.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpawnMatch;
+
+/// Replaces the current match with a fresh one, without leaving the current state.
+#[derive(Event)]
+pub struct RestartMatch;
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_state::<GameState>().add_plugins((
-            LoadingPlugin,
-            MenuPlugin,
-            ActionsPlugin,
-            InternalAudioPlugin,
-            PlayerPlugin,
-            PhysicsPlugin,
-            BoundariesPlugin,
-        ));
+        app.add_state::<GameState>()
+            .init_schedule(SpawnMatch)
+            .add_event::<RestartMatch>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_match.run_if(not(any_with_component::<MatchEntity>())),
+            )
+            .add_systems(Update, restart_match.run_if(on_event::<RestartMatch>()))
+            .add_plugins((
+                LoadingPlugin,
+                LevelPlugin,
+                ShipPlugin,
+                MenuPlugin,
+                MatchSetupPlugin,
+                ShipSelectPlugin,
+                PausePlugin,
+                ResultsPlugin,
+                SettingsPlugin,
+                ActionsPlugin,
+                InternalAudioPlugin,
+                MusicPlugin,
+            ))
+            .add_plugins((
+                PlayerPlugin,
+                ModesPlugin,
+                PowerUpsPlugin,
+                PhysicsPlugin,
+                BoundariesPlugin,
+                CameraPlugin,
+                TrajectoriesPlugin,
+                DebugPlugin,
+                LaunchPlugin,
+                RngPlugin,
+            ));
 
         #[cfg(debug_assertions)]
         {
@@ -55,3 +145,29 @@ impl Plugin for GamePlugin {
         }
     }
 }
+
+fn spawn_match(world: &mut World) {
+    world.run_schedule(SpawnMatch);
+}
+
+fn restart_match(world: &mut World) {
+    world.resource_mut::<Events<RestartMatch>>().clear();
+    let match_entities: Vec<Entity> = world
+        .query_filtered::<Entity, With<MatchEntity>>()
+        .iter(world)
+        .collect();
+    for entity in match_entities {
+        world.entity_mut(entity).despawn_recursive();
+    }
+    world.run_schedule(SpawnMatch);
+}
+
+/// Despawns every entity belonging to the current match.
+pub fn despawn_match_entities(
+    commands: &mut Commands,
+    match_entities: &Query<Entity, With<MatchEntity>>,
+) {
+    for entity in match_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,21 +1,37 @@
 use crate::GameState;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
+use serde::Deserialize;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use crate::level::Level;
+use crate::ship::ShipClass;
 
 pub struct LoadingPlugin;
 
 /// This plugin loads all assets using [`AssetLoader`] from a third party bevy plugin
 /// Alternatively you can write the logic to load assets yourself
 /// If interested, take a look at <https://bevy-cheatbook.github.io/features/assets.html>
+/// If anything fails to load, the errors are shown during `GameState::LoadingFailed`.
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::Menu)
-                .load_collection::<AudioAssets>()
-                .load_collection::<TextureAssets>(),
-        );
+        app.init_resource::<LoadErrors>()
+            .add_loading_state(
+                LoadingState::new(GameState::Loading)
+                    .continue_to_state(GameState::Menu)
+                    .on_failure_continue_to_state(GameState::LoadingFailed)
+                    .load_collection::<AudioAssets>()
+                    .load_collection::<TextureAssets>()
+                    .load_collection::<LevelAssets>()
+                    .load_collection::<ShipAssets>(),
+            )
+            .add_systems(OnEnter(GameState::LoadingFailed), show_load_errors);
     }
 }
 
@@ -26,6 +42,18 @@ impl Plugin for LoadingPlugin {
 pub struct AudioAssets {
     #[asset(path = "audio/flying.ogg")]
     pub flying: Handle<AudioSource>,
+    #[asset(path = "audio/shoot.wav")]
+    pub shoot: Handle<AudioSource>,
+    #[asset(path = "audio/impact.wav")]
+    pub impact: Handle<AudioSource>,
+    #[asset(path = "audio/explosion.wav")]
+    pub explosion: Handle<AudioSource>,
+    #[asset(path = "audio/menu_theme.wav")]
+    pub menu_theme: Handle<AudioSource>,
+    #[asset(path = "audio/battle_theme.wav")]
+    pub battle_theme: Handle<AudioSource>,
+    #[asset(path = "audio/battle_intense.wav")]
+    pub battle_intense: Handle<AudioSource>,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -35,3 +63,170 @@ pub struct TextureAssets {
     #[asset(path = "textures/github.png")]
     pub github: Handle<Image>,
 }
+
+#[derive(AssetCollection, Resource)]
+pub struct LevelAssets {
+    /// The first level is selected by default
+    #[asset(
+        paths(
+            "levels/classic.level.ron",
+            "levels/twin_suns.level.ron",
+            "levels/fortress.level.ron",
+            "levels/binary.level.ron",
+            "levels/maelstrom.level.ron",
+            "levels/crossroads.level.ron"
+        ),
+        collection(typed)
+    )]
+    pub levels: Vec<Handle<Level>>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct ShipAssets {
+    /// The first ship class is selected by default
+    #[asset(
+        paths(
+            "ships/fighter.ship.ron",
+            "ships/interceptor.ship.ron",
+            "ships/gunship.ship.ron"
+        ),
+        collection(typed)
+    )]
+    pub classes: Vec<Handle<ShipClass>>,
+}
+
+/// Game data described in RON files, checked for mistakes when loaded.
+pub trait RonAsset: Asset + for<'de> Deserialize<'de> {
+    /// Extensions of the files, without the leading dot.
+    const EXTENSIONS: &'static [&'static str];
+
+    /// Explains what's wrong with the asset, if anything.
+    fn validate(&self) -> Result<(), String>;
+
+    /// Starts loading anything the asset refers to, like images.
+    fn load_dependencies(&mut self, _load_context: &mut LoadContext) {}
+}
+
+/// Errors reported by [`RonAssetLoader`]s, to be shown if loading fails.
+#[derive(Resource, Clone, Default)]
+pub struct LoadErrors(Arc<Mutex<Vec<String>>>);
+
+impl LoadErrors {
+    fn report(&self, error: String) {
+        error!("{error}");
+        if let Ok(mut errors) = self.0.lock() {
+            errors.push(error);
+        }
+    }
+}
+
+pub struct RonAssetLoader<A> {
+    errors: LoadErrors,
+    asset: PhantomData<fn() -> A>,
+}
+
+impl<A> FromWorld for RonAssetLoader<A> {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            errors: world
+                .get_resource_or_insert_with(LoadErrors::default)
+                .clone(),
+            asset: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RonAssetError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl fmt::Display for RonAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RonAssetError::Io(error) => write!(f, "could not read file: {error}"),
+            RonAssetError::Ron(error) => write!(f, "could not parse file: {error}"),
+            RonAssetError::Invalid(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for RonAssetError {}
+
+impl<A: RonAsset> AssetLoader for RonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonAssetError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<A, RonAssetError>> {
+        Box::pin(async move {
+            let result = async {
+                let mut bytes = Vec::new();
+                reader
+                    .read_to_end(&mut bytes)
+                    .await
+                    .map_err(RonAssetError::Io)?;
+                let asset: A = ron::de::from_bytes(&bytes).map_err(RonAssetError::Ron)?;
+                asset.validate().map_err(RonAssetError::Invalid)?;
+                Ok(asset)
+            }
+            .await;
+            match result {
+                Ok(mut asset) => {
+                    asset.load_dependencies(load_context);
+                    Ok(asset)
+                }
+                Err(error) => {
+                    self.errors
+                        .report(format!("{}: {error}", load_context.path().display()));
+                    Err(error)
+                }
+            }
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        A::EXTENSIONS
+    }
+}
+
+fn show_load_errors(mut commands: Commands, errors: Res<LoadErrors>) {
+    let mut lines = vec!["The game data could not be loaded:".to_string()];
+    match errors.0.lock() {
+        Ok(errors) if !errors.is_empty() => lines.extend(errors.iter().cloned()),
+        _ => lines.push("see the log for details".to_string()),
+    }
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(10.),
+                padding: UiRect::all(Val::Px(20.)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|children| {
+            for line in lines {
+                children.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            }
+        });
+}
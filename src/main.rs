@@ -1,36 +1,89 @@
 // disable console on windows for release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod cli;
+
+use bevy::app::ScheduleRunnerPlugin;
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
-use bevy::winit::WinitWindows;
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::{ExitCondition, PrimaryWindow};
+use bevy::winit::{WinitPlugin, WinitWindows};
 use bevy::DefaultPlugins;
-use star_fighters::GamePlugin;
+use cli::{Cli, Parsed};
+use star_fighters::{GamePlugin, Settings};
 use std::io::Cursor;
+use std::time::Duration;
 use winit::window::Icon;
 
+/// Headless runs simulate time in steps of this length, as fast as they can.
+const HEADLESS_TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
 fn main() {
-    App::new()
-        .insert_resource(Msaa::Off)
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(Parsed::Run(cli)) => cli,
+        Ok(Parsed::Help) => {
+            print!("{}", cli::HELP);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\nRun with `--help` to see the available options.");
+            std::process::exit(2);
+        }
+    };
+    let mut settings = Settings::load();
+    let video = settings.video.launched_with(&cli.launch);
+
+    let headless = cli.launch.headless;
+    let mut app = App::new();
+    app.insert_resource(Msaa::Off)
         .insert_resource(AssetMetaCheck::Never)
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Star Fighters".to_string(),
-                // Bind to canvas included in `index.html`
-                canvas: Some("#bevy".to_owned()),
-                // The canvas size is constrained in index.html and build/web/styles.css
-                fit_canvas_to_parent: true,
-                // Tells wasm not to override default event handling, like F5 and Ctrl+R
-                prevent_default_event_handling: false,
-                ..default()
-            }),
+        .insert_resource(cli.launch);
+    if let Some(match_config) = cli.match_config {
+        app.insert_resource(match_config);
+    }
+    if headless {
+        settings.audio.master_volume = 0.;
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }
+                    .into(),
+                })
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_TICK));
+    } else {
+        let mut window = Window {
+            title: "Star Fighters".to_string(),
+            // Bind to canvas included in `index.html`
+            canvas: Some("#bevy".to_owned()),
+            // The canvas size is constrained in index.html and build/web/styles.css
+            fit_canvas_to_parent: true,
+            // Tells wasm not to override default event handling, like F5 and Ctrl+R
+            prevent_default_event_handling: false,
+            ..default()
+        };
+        video.apply(&mut window);
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(window),
             ..default()
         }))
-        .add_plugins(GamePlugin)
-        .add_systems(Startup, set_window_icon)
-        .run();
+        .add_systems(Startup, set_window_icon);
+    }
+    app.insert_resource(settings).add_plugins(GamePlugin).run();
 }
 
 // Sets the icon on windows and X11
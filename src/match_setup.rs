@@ -0,0 +1,461 @@
+use crate::actions::Pilot;
+use crate::boundaries::BoundaryMode;
+use crate::level::{Level, SelectedLevel, SpawnPlacement};
+use crate::loading::LevelAssets;
+use crate::menu::ButtonColors;
+use crate::modes::GameMode;
+use crate::physics::GravityMode;
+use crate::player::{PlayerCount, MAX_PLAYERS};
+use crate::powerups::PowerUpFrequency;
+use crate::ship::WeaponSet;
+use crate::teams::TeamMode;
+use crate::GameState;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+pub struct MatchSetupPlugin;
+
+/// This plugin draws the screen where the rules of the next match are picked,
+/// between the menu and the ship selection.
+/// Every option is a button cycling through its values.
+impl Plugin for MatchSetupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchConfig>()
+            .add_systems(OnEnter(GameState::MatchSetup), setup_match_setup)
+            .add_systems(
+                Update,
+                (
+                    click_match_setup_button,
+                    update_match_options.run_if(
+                        resource_exists_and_changed::<SelectedLevel>()
+                            .or_else(resource_changed::<PlayerCount>())
+                            .or_else(resource_changed::<TeamMode>())
+                            .or_else(resource_changed::<GravityMode>())
+                            .or_else(resource_changed::<MatchConfig>())
+                            .or_else(state_changed::<GameMode>())
+                            // Right after the screen is set up
+                            .or_else(state_changed::<GameState>()),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::MatchSetup)),
+            )
+            .add_systems(OnExit(GameState::MatchSetup), cleanup_match_setup);
+    }
+}
+
+/// The rules of the next match.
+/// The mode, level, players, sides and gravity mode are picked on the same screen,
+/// but keep their own resources.
+/// The native binary can read it from a RON file, where missing fields keep their default.
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MatchConfig {
+    /// Rounds a side has to win to win a dogfight.
+    pub rounds_to_win: u32,
+    /// Lives every player starts a stock match with.
+    pub stock: u32,
+    /// Scales the pull of every star, levels are made for 1.
+    pub gravity: f32,
+    pub weapon_set: WeaponSet,
+    pub power_ups: PowerUpFrequency,
+    /// Replaces the boundary mode of the level, if set.
+    pub boundary_mode: Option<BoundaryMode>,
+    pub spawns: SpawnPlacement,
+    /// Who flies the ship of every player, see [`crate::player::Player::index`].
+    /// Files list them like `[Ai, Human]`, players left out are human.
+    #[serde(deserialize_with = "deserialize_pilots")]
+    pub pilots: [Pilot; MAX_PLAYERS],
+    /// Seeds the [`crate::GameRng`], every match gets a fresh seed when unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            rounds_to_win: 3,
+            stock: 3,
+            gravity: 1.,
+            weapon_set: WeaponSet::default(),
+            power_ups: PowerUpFrequency::default(),
+            boundary_mode: None,
+            spawns: SpawnPlacement::default(),
+            pilots: [Pilot::Human; MAX_PLAYERS],
+            seed: None,
+        }
+    }
+}
+
+fn deserialize_pilots<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[Pilot; MAX_PLAYERS], D::Error> {
+    let listed = Vec::<Pilot>::deserialize(deserializer)?;
+    if listed.len() > MAX_PLAYERS {
+        return Err(D::Error::custom(format!(
+            "at most {MAX_PLAYERS} pilots can be listed"
+        )));
+    }
+    let mut pilots = [Pilot::Human; MAX_PLAYERS];
+    pilots[..listed.len()].copy_from_slice(&listed);
+    Ok(pilots)
+}
+
+impl MatchConfig {
+    /// Reads a match configuration from a RON file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let config: Self = ron::from_str(&contents).map_err(|error| error.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.rounds_to_win == 0 {
+            return Err("rounds_to_win must be at least 1".to_string());
+        }
+        if self.stock == 0 {
+            return Err("stock must be at least 1".to_string());
+        }
+        if !(self.gravity.is_finite() && self.gravity >= 0.) {
+            return Err(format!("gravity can't be negative, got {}", self.gravity));
+        }
+        Ok(())
+    }
+}
+
+const ROUNDS_TO_WIN: [u32; 4] = [1, 3, 5, 7];
+const STOCKS: [u32; 4] = [1, 3, 5, 7];
+const GRAVITY_STRENGTHS: [f32; 5] = [0.5, 0.75, 1., 1.5, 2.];
+
+/// The value after `current` in `values`, going back to the first one after the last.
+fn next_value<T: Copy + PartialEq>(values: &[T], current: T) -> T {
+    let index = values.iter().position(|value| *value == current);
+    values[index.map_or(0, |index| (index + 1) % values.len())]
+}
+
+#[derive(Component)]
+struct MatchSetupScreen;
+
+/// Buttons cycling through the values of an option of the next match.
+#[derive(Component, Clone, Copy)]
+enum MatchOption {
+    GameMode,
+    Level,
+    Spawns,
+    PlayerCount,
+    TeamMode,
+    Rounds,
+    Stock,
+    GravityMode,
+    GravityStrength,
+    WeaponSet,
+    PowerUps,
+    BoundaryMode,
+    /// Who flies the ship of a player, by player index.
+    Pilot(usize),
+}
+
+/// Text showing the current value of a [`MatchOption`].
+#[derive(Component)]
+struct MatchOptionValue(MatchOption);
+
+#[derive(Component, Clone, Copy)]
+enum NavigationButton {
+    Back,
+    Continue,
+}
+
+#[derive(SystemParam)]
+struct MatchOptions<'w> {
+    selected_level: ResMut<'w, SelectedLevel>,
+    level_assets: Res<'w, LevelAssets>,
+    levels: Res<'w, Assets<Level>>,
+    player_count: ResMut<'w, PlayerCount>,
+    team_mode: ResMut<'w, TeamMode>,
+    gravity_mode: ResMut<'w, GravityMode>,
+    config: ResMut<'w, MatchConfig>,
+    game_mode: Res<'w, State<GameMode>>,
+    next_game_mode: ResMut<'w, NextState<GameMode>>,
+}
+
+fn option_label(option: MatchOption, options: &MatchOptions) -> String {
+    let config = &options.config;
+    match option {
+        MatchOption::GameMode => format!("Mode: {}", options.game_mode.label()),
+        MatchOption::Level => {
+            let name = options
+                .levels
+                .get(&options.selected_level.0)
+                .map_or("?", |level| level.name.as_str());
+            format!("Level: {name}")
+        }
+        MatchOption::Spawns => format!("Spawns: {}", config.spawns.label()),
+        MatchOption::PlayerCount => format!("Players: {}", options.player_count.0),
+        MatchOption::TeamMode => format!("Sides: {}", options.team_mode.label()),
+        MatchOption::Rounds => format!("Rounds to win: {}", config.rounds_to_win),
+        MatchOption::Stock => format!("Lives: {}", config.stock),
+        MatchOption::GravityMode => format!("Gravity: {}", options.gravity_mode.label()),
+        MatchOption::GravityStrength => format!("Strength: {:.0}%", config.gravity * 100.),
+        MatchOption::WeaponSet => format!("Weapons: {}", config.weapon_set.label()),
+        MatchOption::PowerUps => format!("Power-ups: {}", config.power_ups.label()),
+        MatchOption::BoundaryMode => format!(
+            "Edges: {}",
+            config
+                .boundary_mode
+                .map_or("Level default", |mode| mode.label())
+        ),
+        MatchOption::Pilot(index) => {
+            format!("Player {}: {}", index + 1, config.pilots[index].label())
+        }
+    }
+}
+
+fn cycle_option(option: MatchOption, options: &mut MatchOptions) {
+    match option {
+        MatchOption::GameMode => {
+            let next = options.game_mode.cycle();
+            options.next_game_mode.set(next);
+        }
+        MatchOption::Level => options.selected_level.select_next(&options.level_assets),
+        MatchOption::Spawns => options.config.spawns.cycle(),
+        MatchOption::PlayerCount => options.player_count.cycle(),
+        MatchOption::TeamMode => options.team_mode.cycle(),
+        MatchOption::Rounds => {
+            options.config.rounds_to_win = next_value(&ROUNDS_TO_WIN, options.config.rounds_to_win)
+        }
+        MatchOption::Stock => options.config.stock = next_value(&STOCKS, options.config.stock),
+        MatchOption::GravityMode => options.gravity_mode.cycle(),
+        MatchOption::GravityStrength => {
+            options.config.gravity = next_value(&GRAVITY_STRENGTHS, options.config.gravity)
+        }
+        MatchOption::WeaponSet => options.config.weapon_set.cycle(),
+        MatchOption::PowerUps => options.config.power_ups.cycle(),
+        MatchOption::BoundaryMode => {
+            options.config.boundary_mode = match options.config.boundary_mode {
+                None => Some(BoundaryMode::Wrap),
+                Some(BoundaryMode::Wrap) => Some(BoundaryMode::Bounce),
+                Some(BoundaryMode::Bounce) => None,
+            }
+        }
+        MatchOption::Pilot(index) => options.config.pilots[index].cycle(),
+    }
+}
+
+/// A button with `bundle`, and `text` on the text inside it.
+fn spawn_button(
+    children: &mut ChildBuilder,
+    bundle: impl Bundle,
+    label: String,
+    text: impl Bundle,
+    width: f32,
+) {
+    let button_colors = ButtonColors::default();
+    children
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(width),
+                    height: Val::Px(44.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: button_colors.normal.into(),
+                ..Default::default()
+            },
+            button_colors,
+            bundle,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 26.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+                text,
+            ));
+        });
+}
+
+fn spawn_option(children: &mut ChildBuilder, option: MatchOption, options: &MatchOptions) {
+    spawn_button(
+        children,
+        option,
+        option_label(option, options),
+        MatchOptionValue(option),
+        340.,
+    );
+}
+
+fn row(children: &mut ChildBuilder, spawn_children: impl FnOnce(&mut ChildBuilder)) {
+    children
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(spawn_children);
+}
+
+fn column(children: &mut ChildBuilder, spawn_children: impl FnOnce(&mut ChildBuilder)) {
+    children
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(spawn_children);
+}
+
+fn setup_match_setup(mut commands: Commands, options: MatchOptions) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            MatchSetupScreen,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Match setup",
+                TextStyle {
+                    font_size: 50.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+            row(children, |columns| {
+                column(columns, |column| {
+                    for option in [
+                        MatchOption::GameMode,
+                        MatchOption::Level,
+                        MatchOption::Spawns,
+                        MatchOption::PlayerCount,
+                        MatchOption::TeamMode,
+                        MatchOption::Rounds,
+                    ] {
+                        spawn_option(column, option, &options);
+                    }
+                });
+                column(columns, |column| {
+                    for option in [
+                        MatchOption::GravityMode,
+                        MatchOption::GravityStrength,
+                        MatchOption::WeaponSet,
+                        MatchOption::PowerUps,
+                        MatchOption::BoundaryMode,
+                        // Next to the rounds, the other way to decide a match
+                        MatchOption::Stock,
+                    ] {
+                        spawn_option(column, option, &options);
+                    }
+                });
+            });
+            row(children, |columns| {
+                column(columns, |column| {
+                    for index in (0..MAX_PLAYERS).step_by(2) {
+                        spawn_option(column, MatchOption::Pilot(index), &options);
+                    }
+                });
+                column(columns, |column| {
+                    for index in (1..MAX_PLAYERS).step_by(2) {
+                        spawn_option(column, MatchOption::Pilot(index), &options);
+                    }
+                });
+            });
+            row(children, |buttons| {
+                spawn_button(buttons, NavigationButton::Back, "Back".into(), (), 200.);
+                spawn_button(
+                    buttons,
+                    NavigationButton::Continue,
+                    "Continue".into(),
+                    (),
+                    200.,
+                );
+            });
+        });
+}
+
+fn click_match_setup_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut options: MatchOptions,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            Option<&MatchOption>,
+            Option<&NavigationButton>,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button_colors, match_option, navigation) in &mut interaction_query
+    {
+        match *interaction {
+            Interaction::Pressed => {
+                if let Some(option) = match_option {
+                    cycle_option(*option, &mut options);
+                }
+                match navigation {
+                    Some(NavigationButton::Back) => next_state.set(GameState::Menu),
+                    Some(NavigationButton::Continue) => next_state.set(GameState::ShipSelect),
+                    None => {}
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+/// Refreshes the labels, and only shows the pilots of the players taking part.
+fn update_match_options(
+    options: MatchOptions,
+    mut text_query: Query<(&mut Text, &MatchOptionValue)>,
+    mut button_query: Query<(&MatchOption, &mut Style)>,
+) {
+    for (mut text, value) in &mut text_query {
+        text.sections[0].value = option_label(value.0, &options);
+    }
+    for (option, mut style) in &mut button_query {
+        if let MatchOption::Pilot(index) = option {
+            style.display = if *index < options.player_count.0 {
+                Display::Flex
+            } else {
+                Display::None
+            };
+        }
+    }
+}
+
+fn cleanup_match_setup(mut commands: Commands, screen: Query<Entity, With<MatchSetupScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
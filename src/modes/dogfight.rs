@@ -0,0 +1,137 @@
+use super::{
+    hud_text, sides_left, spawn_mode_hud, tick_round_end, GameMode, MatchResult, ModeHud, RoundEnd,
+};
+use crate::match_setup::MatchConfig;
+use crate::player::{Player, PlayerCount, MAX_PLAYERS};
+use crate::teams::TeamMode;
+use crate::{spawn_match, GameState, MatchEntity, RestartMatch};
+use bevy::prelude::*;
+
+pub struct DogfightPlugin;
+
+/// This plugin runs matches made of rounds, either free-for-all or in teams.
+/// A round ends once the ships left all belong to the same side (see [`TeamMode`]),
+/// that side scores a point and the next round starts.
+/// The first side to win as many rounds as the [`MatchConfig`] says wins the match.
+impl Plugin for DogfightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scores>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    // Only for new matches, resuming or starting another round keeps the scores
+                    reset_scores
+                        .before(spawn_match)
+                        .run_if(not(any_with_component::<MatchEntity>())),
+                    spawn_scoreboard.run_if(not(any_with_component::<ModeHud>())),
+                )
+                    .run_if(in_state(GameMode::Dogfight)),
+            )
+            .add_systems(
+                Update,
+                (
+                    end_round,
+                    update_scoreboard.run_if(resource_changed::<Scores>()),
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(GameMode::Dogfight)),
+            );
+    }
+}
+
+/// Rounds won by every side in the current match, see [`TeamMode::side`].
+#[derive(Resource, Default)]
+pub struct Scores(pub [u32; MAX_PLAYERS]);
+
+impl Scores {
+    /// The side that won the match, if any.
+    pub fn winner(&self, rounds_to_win: u32) -> Option<usize> {
+        self.0.iter().position(|score| *score >= rounds_to_win)
+    }
+
+    /// Whether a single round win is enough for some side to win the match.
+    pub fn match_point(&self, rounds_to_win: u32) -> bool {
+        self.0.iter().any(|score| *score + 1 >= rounds_to_win)
+    }
+}
+
+/// Text showing the score of a side.
+#[derive(Component)]
+struct ScoreText(usize);
+
+fn reset_scores(mut scores: ResMut<Scores>) {
+    *scores = Scores::default();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn end_round(
+    mut round_end: ResMut<RoundEnd>,
+    mut scores: ResMut<Scores>,
+    mut match_result: ResMut<MatchResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut restart: EventWriter<RestartMatch>,
+    ships: Query<&Player>,
+    team_mode: Res<TeamMode>,
+    player_count: Res<PlayerCount>,
+    config: Res<MatchConfig>,
+    time: Res<Time>,
+) {
+    let sides_left = sides_left(ships.iter(), &team_mode);
+    let Some(winners) = tick_round_end(&mut round_end, sides_left, &time) else {
+        return;
+    };
+    for side in winners {
+        scores.0[side] += 1;
+    }
+    let Some(winner) = scores.winner(config.rounds_to_win) else {
+        restart.send(RestartMatch);
+        return;
+    };
+    *match_result = MatchResult {
+        headline: format!("{} wins!", team_mode.side_name(winner)),
+        color: team_mode.side_color(winner),
+        details: (0..team_mode.sides(player_count.0))
+            .map(|side| {
+                (
+                    format!("{}: {} rounds", team_mode.side_name(side), scores.0[side]),
+                    team_mode.side_color(side),
+                )
+            })
+            .collect(),
+    };
+    next_state.set(GameState::Results);
+}
+
+fn score_label(team_mode: &TeamMode, side: usize, score: u32) -> String {
+    format!("{}: {score}", team_mode.side_name(side))
+}
+
+fn spawn_scoreboard(
+    mut commands: Commands,
+    player_count: Res<PlayerCount>,
+    team_mode: Res<TeamMode>,
+    scores: Res<Scores>,
+) {
+    let hud = spawn_mode_hud(&mut commands);
+    commands.entity(hud).with_children(|children| {
+        for side in 0..team_mode.sides(player_count.0) {
+            children.spawn((
+                hud_text(
+                    score_label(&team_mode, side, scores.0[side]),
+                    team_mode.side_color(side),
+                ),
+                ScoreText(side),
+            ));
+        }
+    });
+}
+
+fn update_scoreboard(
+    scores: Res<Scores>,
+    team_mode: Res<TeamMode>,
+    mut texts: Query<(&mut Text, &ScoreText)>,
+) {
+    for (mut text, score_text) in &mut texts {
+        text.sections[0].value = score_label(&team_mode, score_text.0, scores.0[score_text.0]);
+    }
+}
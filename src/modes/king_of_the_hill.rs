@@ -0,0 +1,219 @@
+use super::{
+    hud_text, sides_left, spawn_mode_hud, tick_round_end, GameMode, MatchResult, ModeHud, RoundEnd,
+    TEXT_COLOR,
+};
+use crate::level::{Level, SelectedLevel};
+use crate::player::{Player, PlayerCount, MAX_PLAYERS};
+use crate::teams::TeamMode;
+use crate::{spawn_match, GameState, MatchEntity, RestartMatch, SpawnMatch};
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+
+pub struct KingOfTheHillPlugin;
+
+/// This plugin runs king of the hill matches: a zone orbits the star, and every second
+/// a side spends alone inside it counts towards [`HILL_TIME_TO_WIN`].
+/// Once only one side has ships left, the round restarts without resetting the times.
+impl Plugin for KingOfTheHillPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HillTimes>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    reset_hill_times
+                        .before(spawn_match)
+                        .run_if(not(any_with_component::<MatchEntity>())),
+                    spawn_hill_hud.run_if(not(any_with_component::<ModeHud>())),
+                )
+                    .run_if(in_state(GameMode::KingOfTheHill)),
+            )
+            .add_systems(
+                SpawnMatch,
+                spawn_hill.run_if(in_state(GameMode::KingOfTheHill)),
+            )
+            .add_systems(
+                Update,
+                (move_hill, hold_hill, restart_round, update_hill_hud)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(GameMode::KingOfTheHill)),
+            );
+    }
+}
+
+/// Seconds a side has to hold the hill to win the match.
+const HILL_TIME_TO_WIN: f32 = 20.;
+const HILL_RADIUS: f32 = 90.;
+/// Radians per second the hill moves along its orbit.
+const HILL_ORBIT_SPEED: f32 = 0.15;
+/// How far from the star the hill orbits, relative to the widest orbit that fits the arena.
+const HILL_ORBIT_SCALE: f32 = 0.5;
+
+/// Seconds every side has held the hill, see [`TeamMode::side`].
+#[derive(Resource, Default)]
+struct HillTimes([f32; MAX_PLAYERS]);
+
+#[derive(Component)]
+struct Hill {
+    center: Vec2,
+    distance: f32,
+    angle: f32,
+}
+
+/// Text showing the time a side held the hill.
+#[derive(Component)]
+struct HillTimeText(usize);
+
+fn reset_hill_times(mut hill_times: ResMut<HillTimes>) {
+    *hill_times = HillTimes::default();
+}
+
+fn spawn_hill(
+    mut commands: Commands,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let level = levels
+        .get(&selected_level.0)
+        .expect("Levels are loaded before leaving `GameState::Loading`");
+    let hill = Hill {
+        center: level.stars.first().map_or(Vec2::ZERO, |star| star.position),
+        distance: level.max_orbit_distance() * HILL_ORBIT_SCALE,
+        angle: std::f32::consts::FRAC_PI_2,
+    };
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(HILL_RADIUS).into()).into(),
+            material: materials.add(hill_color(TEXT_COLOR).into()),
+            // Behind the ships
+            transform: Transform::from_translation(hill_position(&hill).extend(-1.)),
+            ..default()
+        },
+        hill,
+        MatchEntity,
+    ));
+}
+
+fn hill_position(hill: &Hill) -> Vec2 {
+    hill.center + Vec2::from_angle(hill.angle) * hill.distance
+}
+
+/// The hill is drawn see-through, so the ships inside can still be seen.
+fn hill_color(color: Color) -> Color {
+    color.with_a(0.25)
+}
+
+fn move_hill(mut hills: Query<(&mut Hill, &mut Transform)>, time: Res<Time>) {
+    for (mut hill, mut transform) in &mut hills {
+        hill.angle += HILL_ORBIT_SPEED * time.delta_seconds();
+        transform.translation = hill_position(&hill).extend(transform.translation.z);
+    }
+}
+
+/// Gives time to the side holding the hill, as long as no other side contests it.
+/// The hill takes the color of the side holding it.
+#[allow(clippy::too_many_arguments)]
+fn hold_hill(
+    mut hill_times: ResMut<HillTimes>,
+    mut match_result: ResMut<MatchResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    hills: Query<(&Transform, &Handle<ColorMaterial>), With<Hill>>,
+    ships: Query<(&Player, &Transform)>,
+    team_mode: Res<TeamMode>,
+    player_count: Res<PlayerCount>,
+    time: Res<Time>,
+) {
+    for (hill_transform, material) in &hills {
+        let inside = ships.iter().filter(|(_, transform)| {
+            transform
+                .translation
+                .truncate()
+                .distance(hill_transform.translation.truncate())
+                <= HILL_RADIUS
+        });
+        let sides = sides_left(inside.map(|(player, _)| player), &team_mode);
+        let holder = match sides[..] {
+            [side] => Some(side),
+            _ => None,
+        };
+        if let Some(material) = materials.get_mut(material) {
+            material.color =
+                hill_color(holder.map_or(TEXT_COLOR, |side| team_mode.side_color(side)));
+        }
+        let Some(side) = holder else {
+            continue;
+        };
+        hill_times.0[side] += time.delta_seconds();
+        if hill_times.0[side] >= HILL_TIME_TO_WIN {
+            *match_result = MatchResult {
+                headline: format!("{} is king of the hill!", team_mode.side_name(side)),
+                color: team_mode.side_color(side),
+                details: (0..team_mode.sides(player_count.0))
+                    .map(|side| {
+                        (
+                            format!("{}: {:.1} s", team_mode.side_name(side), hill_times.0[side]),
+                            team_mode.side_color(side),
+                        )
+                    })
+                    .collect(),
+            };
+            next_state.set(GameState::Results);
+        }
+    }
+}
+
+/// Brings everybody back once a single side is left, so the hill stays contested.
+fn restart_round(
+    mut round_end: ResMut<RoundEnd>,
+    mut restart: EventWriter<RestartMatch>,
+    ships: Query<&Player>,
+    team_mode: Res<TeamMode>,
+    time: Res<Time>,
+) {
+    let sides_left = sides_left(ships.iter(), &team_mode);
+    if tick_round_end(&mut round_end, sides_left, &time).is_some() {
+        restart.send(RestartMatch);
+    }
+}
+
+fn hill_time_label(team_mode: &TeamMode, side: usize, time: f32) -> String {
+    format!(
+        "{}: {:.0}/{HILL_TIME_TO_WIN:.0} s",
+        team_mode.side_name(side),
+        time.floor()
+    )
+}
+
+fn spawn_hill_hud(
+    mut commands: Commands,
+    player_count: Res<PlayerCount>,
+    team_mode: Res<TeamMode>,
+    hill_times: Res<HillTimes>,
+) {
+    let hud = spawn_mode_hud(&mut commands);
+    commands.entity(hud).with_children(|children| {
+        for side in 0..team_mode.sides(player_count.0) {
+            children.spawn((
+                hud_text(
+                    hill_time_label(&team_mode, side, hill_times.0[side]),
+                    team_mode.side_color(side),
+                ),
+                HillTimeText(side),
+            ));
+        }
+    });
+}
+
+fn update_hill_hud(
+    hill_times: Res<HillTimes>,
+    team_mode: Res<TeamMode>,
+    mut texts: Query<(&mut Text, &HillTimeText)>,
+) {
+    for (mut text, hill_time_text) in &mut texts {
+        text.sections[0].value =
+            hill_time_label(&team_mode, hill_time_text.0, hill_times.0[hill_time_text.0]);
+    }
+}
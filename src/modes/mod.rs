@@ -0,0 +1,223 @@
+use crate::level::{Level, SelectedLevel, SpawnPoint};
+use crate::physics::{Collider, Mass, PhysicsBundle, Velocity};
+use crate::player::Player;
+use crate::teams::TeamMode;
+use crate::{GameState, MatchEntity, SpawnMatch};
+use bevy::prelude::*;
+
+mod dogfight;
+mod king_of_the_hill;
+mod stock;
+mod survival;
+mod time_attack;
+
+pub use dogfight::Scores;
+pub use stock::Stocks;
+
+use dogfight::DogfightPlugin;
+use king_of_the_hill::KingOfTheHillPlugin;
+use stock::StockPlugin;
+use survival::SurvivalPlugin;
+use time_attack::TimeAttackPlugin;
+
+pub struct ModesPlugin;
+
+/// This plugin adds the rules of every game mode on top of `GameState::Playing`.
+/// Every mode has its own plugin, with systems that only run while its [`GameMode`] is selected.
+/// Modes decide when a match is over and fill in the [`MatchResult`] shown afterwards.
+impl Plugin for ModesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameMode>()
+            .init_resource::<MatchResult>()
+            .init_resource::<TeamMode>()
+            .init_resource::<RoundEnd>()
+            .add_systems(SpawnMatch, reset_round_end)
+            .add_systems(OnEnter(GameState::Menu), despawn_mode_hud)
+            .add_systems(OnEnter(GameState::Results), despawn_mode_hud)
+            .add_plugins((
+                DogfightPlugin,
+                KingOfTheHillPlugin,
+                StockPlugin,
+                SurvivalPlugin,
+                TimeAttackPlugin,
+            ));
+    }
+}
+
+#[derive(States, Default, Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum GameMode {
+    // Last side standing wins the round, rounds decide the match
+    #[default]
+    Dogfight,
+    // Destroyed ships respawn until a player runs out of lives
+    Stock,
+    // Sides score while they're alone inside a zone orbiting the star
+    KingOfTheHill,
+    // All players against waves of drones
+    Survival,
+    // All players racing the clock to destroy every target
+    TimeAttack,
+}
+
+impl GameMode {
+    pub fn cycle(&self) -> Self {
+        match self {
+            GameMode::Dogfight => GameMode::Stock,
+            GameMode::Stock => GameMode::KingOfTheHill,
+            GameMode::KingOfTheHill => GameMode::Survival,
+            GameMode::Survival => GameMode::TimeAttack,
+            GameMode::TimeAttack => GameMode::Dogfight,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Dogfight => "Dogfight",
+            GameMode::Stock => "Stock",
+            GameMode::KingOfTheHill => "King of the hill",
+            GameMode::Survival => "Survival",
+            GameMode::TimeAttack => "Time attack",
+        }
+    }
+}
+
+/// What the results screen shows about the last match.
+#[derive(Resource)]
+pub struct MatchResult {
+    pub headline: String,
+    pub color: Color,
+    /// Lines below the headline, like the final scores.
+    pub details: Vec<(String, Color)>,
+}
+
+impl Default for MatchResult {
+    fn default() -> Self {
+        Self {
+            headline: String::new(),
+            color: TEXT_COLOR,
+            details: vec![],
+        }
+    }
+}
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+/// Marks the heads-up display of the current mode.
+/// It's spawned when a match starts and stays through its rounds until the match is left.
+#[derive(Component)]
+struct ModeHud;
+
+fn spawn_mode_hud(commands: &mut Commands) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(40.),
+                    ..default()
+                },
+                ..default()
+            },
+            ModeHud,
+        ))
+        .id()
+}
+
+fn hud_text(value: String, color: Color) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 30.0,
+            color,
+            ..default()
+        },
+    )
+}
+
+/// The level the match is played on.
+fn current_level<'a>(selected_level: &SelectedLevel, levels: &'a Assets<Level>) -> &'a Level {
+    levels
+        .get(&selected_level.0)
+        .expect("Levels are loaded before leaving `GameState::Loading`")
+}
+
+/// A square body put in orbit by the mode, like a drone or a target.
+/// It crashes into whatever it touches.
+fn orbiting_body(
+    spawn_point: &SpawnPoint,
+    size: f32,
+    mass: f32,
+    color: Color,
+) -> (SpriteBundle, PhysicsBundle, Collider, MatchEntity) {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            transform: Transform::from_translation(spawn_point.position.extend(0.))
+                .with_rotation(Quat::from_rotation_z(spawn_point.rotation)),
+            ..default()
+        },
+        PhysicsBundle {
+            mass: Mass(mass),
+            velocity: Velocity(spawn_point.velocity.unwrap_or_default().extend(0.)),
+            ..default()
+        },
+        Collider {
+            dimensions: Vec2::splat(size),
+            destroyable: true,
+            restitution: None,
+        },
+        MatchEntity,
+    )
+}
+
+fn despawn_mode_hud(mut commands: Commands, hud: Query<Entity, With<ModeHud>>) {
+    for entity in hud.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Counts down from the moment only one side has ships left in a mode with rounds.
+#[derive(Resource, Default)]
+struct RoundEnd(Option<Timer>);
+
+/// Time between the end of a round and the start of the next one,
+/// so the last explosion can be seen.
+const ROUND_END_DELAY: f32 = 2.;
+
+fn reset_round_end(mut round_end: ResMut<RoundEnd>) {
+    round_end.0 = None;
+}
+
+/// The sides that still have ships in the match, see [`TeamMode::side`].
+fn sides_left<'a>(ships: impl Iterator<Item = &'a Player>, team_mode: &TeamMode) -> Vec<usize> {
+    let mut sides: Vec<usize> = ships.map(|player| team_mode.side(player.index())).collect();
+    sides.sort();
+    sides.dedup();
+    sides
+}
+
+/// Starts counting down once at most one side is left and returns the sides still left
+/// when the countdown runs out, which could be none if the last ships crashed meanwhile.
+fn tick_round_end(
+    round_end: &mut RoundEnd,
+    sides_left: Vec<usize>,
+    time: &Time,
+) -> Option<Vec<usize>> {
+    let Some(timer) = round_end.0.as_mut() else {
+        if sides_left.len() <= 1 {
+            round_end.0 = Some(Timer::from_seconds(ROUND_END_DELAY, TimerMode::Once));
+        }
+        return None;
+    };
+    timer
+        .tick(time.delta())
+        .just_finished()
+        .then_some(sides_left)
+}
@@ -0,0 +1,203 @@
+use super::{
+    hud_text, spawn_mode_hud, GameMode, MatchResult, ModeHud, RoundEnd, ROUND_END_DELAY, TEXT_COLOR,
+};
+use crate::match_setup::MatchConfig;
+use crate::player::{PlayerCount, RespawnShip, ShipDestroyed, MAX_PLAYERS};
+use crate::teams::TeamMode;
+use crate::{spawn_match, GameState, MatchEntity};
+use bevy::prelude::*;
+
+pub struct StockPlugin;
+
+/// This plugin runs stock matches: every player has as many lives as the [`MatchConfig`] says,
+/// and destroyed ships warp back in after [`RESPAWN_DELAY`] as long as lives are left.
+/// The match ends once a player loses their last life, and the side with the most lives
+/// left wins it.
+impl Plugin for StockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stocks>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    // Only for new matches, resuming keeps the lives
+                    reset_stocks
+                        .before(spawn_match)
+                        .run_if(not(any_with_component::<MatchEntity>())),
+                    spawn_stock_hud.run_if(not(any_with_component::<ModeHud>())),
+                )
+                    .run_if(in_state(GameMode::Stock)),
+            )
+            .add_systems(
+                Update,
+                (
+                    lose_lives,
+                    respawn_players,
+                    end_stock_match,
+                    update_stock_hud.run_if(resource_changed::<Stocks>()),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(GameMode::Stock)),
+            );
+    }
+}
+
+/// Seconds between losing a ship and warping back in with a new one.
+const RESPAWN_DELAY: f32 = 3.;
+
+/// Lives left for every player in the current stock match, see [`crate::player::Player::index`].
+#[derive(Resource, Default)]
+pub struct Stocks {
+    lives: [u32; MAX_PLAYERS],
+    /// Counts down to the respawn of players who lost their ship.
+    respawns: [Option<Timer>; MAX_PLAYERS],
+}
+
+impl Stocks {
+    /// Whether one more lost ship could end the match.
+    pub fn last_life(&self, player_count: usize) -> bool {
+        self.lives[..player_count].iter().any(|lives| *lives <= 1)
+    }
+}
+
+/// Text showing the lives left for a player.
+#[derive(Component)]
+struct LivesText(usize);
+
+fn reset_stocks(mut stocks: ResMut<Stocks>, config: Res<MatchConfig>) {
+    *stocks = Stocks {
+        lives: [config.stock; MAX_PLAYERS],
+        ..default()
+    };
+}
+
+fn lose_lives(mut stocks: ResMut<Stocks>, mut destroyed_ships: EventReader<ShipDestroyed>) {
+    for destroyed in destroyed_ships.read() {
+        let player = destroyed.player;
+        // A ship hitting several things at once is still only one life
+        if stocks.lives[player] == 0 || stocks.respawns[player].is_some() {
+            continue;
+        }
+        stocks.lives[player] -= 1;
+        if stocks.lives[player] > 0 {
+            stocks.respawns[player] = Some(Timer::from_seconds(RESPAWN_DELAY, TimerMode::Once));
+        }
+    }
+}
+
+fn respawn_players(
+    mut stocks: ResMut<Stocks>,
+    mut respawns: EventWriter<RespawnShip>,
+    time: Res<Time>,
+) {
+    for player in 0..MAX_PLAYERS {
+        // Counting down doesn't change the lives, the HUD has nothing to refresh
+        let Some(timer) = stocks.bypass_change_detection().respawns[player].as_mut() else {
+            continue;
+        };
+        if timer.tick(time.delta()).just_finished() {
+            stocks.respawns[player] = None;
+            respawns.send(RespawnShip { player });
+        }
+    }
+}
+
+/// Waits a moment after a player runs out of lives, so the last explosion can be seen,
+/// then hands the win to the side with the most lives left.
+fn end_stock_match(
+    mut round_end: ResMut<RoundEnd>,
+    mut match_result: ResMut<MatchResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    stocks: Res<Stocks>,
+    team_mode: Res<TeamMode>,
+    player_count: Res<PlayerCount>,
+    time: Res<Time>,
+) {
+    if stocks.lives[..player_count.0]
+        .iter()
+        .all(|lives| *lives > 0)
+    {
+        return;
+    }
+    let timer = round_end
+        .0
+        .get_or_insert_with(|| Timer::from_seconds(ROUND_END_DELAY, TimerMode::Once));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let side_lives: Vec<u32> = (0..team_mode.sides(player_count.0))
+        .map(|side| {
+            (0..player_count.0)
+                .filter(|player| team_mode.side(*player) == side)
+                .map(|player| stocks.lives[player])
+                .sum()
+        })
+        .collect();
+    let most_lives = side_lives.iter().copied().max().unwrap_or_default();
+    let winners: Vec<usize> = (0..side_lives.len())
+        .filter(|side| side_lives[*side] == most_lives)
+        .collect();
+    let (headline, color) = match winners[..] {
+        [winner] => (
+            format!("{} wins!", team_mode.side_name(winner)),
+            team_mode.side_color(winner),
+        ),
+        _ => ("Draw!".to_string(), TEXT_COLOR),
+    };
+    *match_result = MatchResult {
+        headline,
+        color,
+        details: side_lives
+            .iter()
+            .enumerate()
+            .map(|(side, lives)| {
+                (
+                    format!(
+                        "{}: {} left",
+                        team_mode.side_name(side),
+                        lives_label(*lives)
+                    ),
+                    team_mode.side_color(side),
+                )
+            })
+            .collect(),
+    };
+    next_state.set(GameState::Results);
+}
+
+fn lives_label(lives: u32) -> String {
+    match lives {
+        1 => "1 life".to_string(),
+        lives => format!("{lives} lives"),
+    }
+}
+
+fn player_lives_label(player: usize, lives: u32) -> String {
+    format!("Player {}: {}", player + 1, lives_label(lives))
+}
+
+fn spawn_stock_hud(
+    mut commands: Commands,
+    player_count: Res<PlayerCount>,
+    team_mode: Res<TeamMode>,
+    stocks: Res<Stocks>,
+) {
+    let hud = spawn_mode_hud(&mut commands);
+    commands.entity(hud).with_children(|children| {
+        for player in 0..player_count.0 {
+            children.spawn((
+                hud_text(
+                    player_lives_label(player, stocks.lives[player]),
+                    team_mode.side_color(team_mode.side(player)),
+                ),
+                LivesText(player),
+            ));
+        }
+    });
+}
+
+fn update_stock_hud(stocks: Res<Stocks>, mut texts: Query<(&mut Text, &LivesText)>) {
+    for (mut text, lives_text) in &mut texts {
+        text.sections[0].value = player_lives_label(lives_text.0, stocks.lives[lives_text.0]);
+    }
+}
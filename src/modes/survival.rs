@@ -0,0 +1,206 @@
+use super::{
+    current_level, hud_text, orbiting_body, spawn_mode_hud, GameMode, MatchResult, ModeHud,
+    TEXT_COLOR,
+};
+use crate::level::{Level, SelectedLevel};
+use crate::match_setup::MatchConfig;
+use crate::physics::{apply_forces, ForceSource, Forces};
+use crate::player::Player;
+use crate::{GameState, SpawnMatch};
+use bevy::prelude::*;
+
+pub struct SurvivalPlugin;
+
+/// This plugin runs survival matches: all players fight together against waves of drones
+/// spawned in orbit, which chase the closest ship and crash into it.
+/// Clearing [`WAVES`] waves wins the match, losing every ship ends it.
+impl Plugin for SurvivalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Waves>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_survival_hud
+                    .run_if(not(any_with_component::<ModeHud>()))
+                    .run_if(in_state(GameMode::Survival)),
+            )
+            .add_systems(
+                SpawnMatch,
+                start_first_wave.run_if(in_state(GameMode::Survival)),
+            )
+            .add_systems(
+                Update,
+                (
+                    steer_drones.before(apply_forces),
+                    spawn_waves,
+                    end_survival,
+                    update_survival_hud,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(GameMode::Survival)),
+            );
+    }
+}
+
+const WAVES: u32 = 5;
+/// Seconds between clearing a wave and the next one showing up.
+const WAVE_DELAY: f32 = 3.;
+const DRONE_MASS: f32 = 0.5;
+const DRONE_SIZE: f32 = 24.;
+const DRONE_THRUST: f32 = 60.;
+/// Radians per second.
+const DRONE_TURN_RATE: f32 = 1.5;
+/// Drones only thrust when they're pointing roughly at their target.
+const DRONE_AIM_ANGLE: f32 = 0.4;
+const DRONE_COLOR: Color = Color::rgb(0.75, 0.3, 0.9);
+
+#[derive(Resource, Default)]
+struct Waves {
+    current: u32,
+    /// Counts down to the next wave once the current one is cleared.
+    next: Option<Timer>,
+}
+
+#[derive(Component)]
+struct Drone;
+
+#[derive(Component)]
+struct WaveText;
+
+fn drones_in_wave(wave: u32) -> u32 {
+    2 + wave * 2
+}
+
+/// Spreads the drones of a wave evenly on an orbit, turned a bit more with every wave.
+fn spawn_wave(commands: &mut Commands, level: &Level, gravity: f32, wave: u32) {
+    let count = drones_in_wave(wave);
+    let distance = level.max_orbit_distance() * 0.6;
+    for index in 0..count {
+        let angle = wave as f32 + std::f32::consts::TAU * index as f32 / count as f32;
+        let spawn_point = level.orbit(angle, distance, DRONE_MASS, gravity);
+        commands.spawn((
+            orbiting_body(&spawn_point, DRONE_SIZE, DRONE_MASS, DRONE_COLOR),
+            Drone,
+        ));
+    }
+}
+
+fn start_first_wave(
+    mut commands: Commands,
+    mut waves: ResMut<Waves>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    config: Res<MatchConfig>,
+) {
+    let level = current_level(&selected_level, &levels);
+    *waves = Waves {
+        current: 1,
+        next: None,
+    };
+    spawn_wave(&mut commands, level, config.gravity, waves.current);
+}
+
+/// Turns every drone towards the closest ship and thrusts once it's facing it.
+fn steer_drones(
+    mut drones: Query<(&mut Transform, &mut Forces), (With<Drone>, Without<Player>)>,
+    ships: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut forces) in &mut drones {
+        let position = transform.translation.truncate();
+        let target = ships
+            .iter()
+            .map(|ship| ship.translation.truncate())
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+        let thrust = target.map_or(0., |target| {
+            let forward = transform.up().truncate();
+            let angle = forward.angle_between(target - position);
+            if angle.is_nan() {
+                return 0.;
+            }
+            let max_turn = DRONE_TURN_RATE * time.delta_seconds();
+            transform.rotate_z(angle.clamp(-max_turn, max_turn));
+            if angle.abs() < DRONE_AIM_ANGLE {
+                DRONE_THRUST
+            } else {
+                0.
+            }
+        });
+        forces.set(ForceSource::Thrust, transform.up() * thrust);
+    }
+}
+
+fn spawn_waves(
+    mut commands: Commands,
+    mut waves: ResMut<Waves>,
+    drones: Query<(), With<Drone>>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    config: Res<MatchConfig>,
+    time: Res<Time>,
+) {
+    if !drones.is_empty() || waves.current >= WAVES {
+        return;
+    }
+    let timer = waves
+        .next
+        .get_or_insert_with(|| Timer::from_seconds(WAVE_DELAY, TimerMode::Once));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let level = current_level(&selected_level, &levels);
+    waves.current += 1;
+    waves.next = None;
+    spawn_wave(&mut commands, level, config.gravity, waves.current);
+}
+
+fn end_survival(
+    waves: Res<Waves>,
+    mut match_result: ResMut<MatchResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    drones: Query<(), With<Drone>>,
+    ships: Query<&Player>,
+) {
+    let headline = if ships.is_empty() {
+        "The drones won"
+    } else if drones.is_empty() && waves.current >= WAVES {
+        "Every wave cleared!"
+    } else {
+        return;
+    };
+    let cleared = if drones.is_empty() {
+        waves.current
+    } else {
+        waves.current - 1
+    };
+    *match_result = MatchResult {
+        headline: headline.to_string(),
+        color: TEXT_COLOR,
+        details: vec![(format!("Waves cleared: {cleared}/{WAVES}"), TEXT_COLOR)],
+    };
+    next_state.set(GameState::Results);
+}
+
+fn wave_label(waves: &Waves, drones_left: usize) -> String {
+    format!(
+        "Wave {}/{WAVES} - Drones left: {drones_left}",
+        waves.current
+    )
+}
+
+fn spawn_survival_hud(mut commands: Commands) {
+    let hud = spawn_mode_hud(&mut commands);
+    commands.entity(hud).with_children(|children| {
+        children.spawn((hud_text(String::new(), TEXT_COLOR), WaveText));
+    });
+}
+
+fn update_survival_hud(
+    waves: Res<Waves>,
+    drones: Query<(), With<Drone>>,
+    mut texts: Query<&mut Text, With<WaveText>>,
+) {
+    for mut text in &mut texts {
+        text.sections[0].value = wave_label(&waves, drones.iter().count());
+    }
+}
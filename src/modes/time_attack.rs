@@ -0,0 +1,134 @@
+use super::{
+    current_level, hud_text, orbiting_body, spawn_mode_hud, GameMode, MatchResult, ModeHud,
+    TEXT_COLOR,
+};
+use crate::level::{Level, SelectedLevel};
+use crate::match_setup::MatchConfig;
+use crate::player::Player;
+use crate::{GameState, SpawnMatch};
+use bevy::prelude::*;
+
+pub struct TimeAttackPlugin;
+
+/// This plugin runs time attack matches: all players race the clock together
+/// to destroy [`TARGETS`] targets orbiting the star.
+/// Losing every ship before that ends the match.
+impl Plugin for TimeAttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clock>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_time_attack_hud
+                    .run_if(not(any_with_component::<ModeHud>()))
+                    .run_if(in_state(GameMode::TimeAttack)),
+            )
+            .add_systems(
+                SpawnMatch,
+                spawn_targets.run_if(in_state(GameMode::TimeAttack)),
+            )
+            .add_systems(
+                Update,
+                (tick_clock, end_time_attack, update_time_attack_hud)
+                    .chain()
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(GameMode::TimeAttack)),
+            );
+    }
+}
+
+const TARGETS: u32 = 8;
+const TARGET_MASS: f32 = 1.;
+const TARGET_SIZE: f32 = 30.;
+const TARGET_COLOR: Color = Color::rgb(1.0, 0.75, 0.2);
+/// Targets are spread over orbits between these distances,
+/// relative to the widest orbit that fits the arena.
+const TARGET_ORBITS: (f32, f32) = (0.3, 0.9);
+
+/// Seconds since the first target appeared.
+#[derive(Resource, Default)]
+struct Clock(f32);
+
+#[derive(Component)]
+struct Target;
+
+#[derive(Component)]
+struct ClockText;
+
+fn spawn_targets(
+    mut commands: Commands,
+    mut clock: ResMut<Clock>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    config: Res<MatchConfig>,
+) {
+    let level = current_level(&selected_level, &levels);
+    clock.0 = 0.;
+    let (closest, furthest) = TARGET_ORBITS;
+    for index in 0..TARGETS {
+        let progress = index as f32 / (TARGETS - 1) as f32;
+        let distance = level.max_orbit_distance() * (closest + (furthest - closest) * progress);
+        // Every target starts on a different side of the star
+        let angle = index as f32 * 2.4;
+        let mut spawn_point = level.orbit(angle, distance, TARGET_MASS, config.gravity);
+        // Diamonds, to tell them apart from drones
+        spawn_point.rotation = std::f32::consts::FRAC_PI_4;
+        commands.spawn((
+            orbiting_body(&spawn_point, TARGET_SIZE, TARGET_MASS, TARGET_COLOR),
+            Target,
+        ));
+    }
+}
+
+fn tick_clock(mut clock: ResMut<Clock>, time: Res<Time>) {
+    clock.0 += time.delta_seconds();
+}
+
+fn end_time_attack(
+    clock: Res<Clock>,
+    mut match_result: ResMut<MatchResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+    targets: Query<(), With<Target>>,
+    ships: Query<(), With<Player>>,
+) {
+    let targets_left = targets.iter().count();
+    let headline = if targets_left == 0 {
+        format!("All targets destroyed in {:.1} s!", clock.0)
+    } else if ships.is_empty() {
+        "Out of ships".to_string()
+    } else {
+        return;
+    };
+    *match_result = MatchResult {
+        headline,
+        color: TEXT_COLOR,
+        details: vec![(
+            format!(
+                "Targets destroyed: {}/{TARGETS}",
+                TARGETS as usize - targets_left
+            ),
+            TEXT_COLOR,
+        )],
+    };
+    next_state.set(GameState::Results);
+}
+
+fn spawn_time_attack_hud(mut commands: Commands) {
+    let hud = spawn_mode_hud(&mut commands);
+    commands.entity(hud).with_children(|children| {
+        children.spawn((hud_text(String::new(), TEXT_COLOR), ClockText));
+    });
+}
+
+fn update_time_attack_hud(
+    clock: Res<Clock>,
+    targets: Query<(), With<Target>>,
+    mut texts: Query<&mut Text, With<ClockText>>,
+) {
+    for mut text in &mut texts {
+        text.sections[0].value = format!(
+            "Time: {:.1} s - Targets left: {}",
+            clock.0,
+            targets.iter().count()
+        );
+    }
+}
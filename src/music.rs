@@ -0,0 +1,193 @@
+use crate::audio::Music;
+use crate::loading::AudioAssets;
+use crate::match_setup::MatchConfig;
+use crate::modes::{GameMode, Scores, Stocks};
+use crate::player::{Player, PlayerCount};
+use crate::{GameState, MatchEntity, Settings};
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+use std::time::Duration;
+
+pub struct MusicPlugin;
+
+/// This plugin picks the music for the current `GameState` and crossfades between tracks
+/// when it changes. During a match, the battle music gets more intense as ships get closer
+/// to each other and as the match gets closer to being decided.
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicPlayer>()
+            .init_resource::<MusicIntensity>()
+            .add_systems(
+                Update,
+                (
+                    switch_track,
+                    update_intensity.run_if(in_state(GameState::Playing)),
+                    update_music_volume,
+                )
+                    .chain()
+                    // Also keeps it quiet when loading failed
+                    .run_if(resource_exists::<AudioAssets>()),
+            );
+    }
+}
+
+const CROSSFADE_SECONDS: f32 = 1.5;
+/// Ships closer than this make the music as intense as it gets.
+const CLOSE_DISTANCE: f32 = 150.;
+/// Ships further apart than this don't make the music any more intense.
+const FAR_DISTANCE: f32 = 600.;
+/// The least intensity while the round is about to end: in the final duel of a round
+/// started by more than two ships, or when the next round could decide the match.
+const ROUND_ENDING_INTENSITY: f32 = 0.6;
+/// How much the intensity can change per second, so it ramps instead of jumping around.
+const INTENSITY_RATE: f32 = 0.5;
+/// The music is ducked to this fraction of its volume while the match is paused.
+const PAUSED_VOLUME: f32 = 0.4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Track {
+    Menu,
+    Battle,
+}
+
+impl Track {
+    /// The track that should play in the given state.
+    fn for_state(state: &GameState, match_in_progress: bool) -> Option<Self> {
+        match state {
+            GameState::Loading | GameState::LoadingFailed => None,
+            GameState::Menu
+            | GameState::MatchSetup
+            | GameState::ShipSelect
+            | GameState::Results => Some(Track::Menu),
+            GameState::Playing | GameState::Paused => Some(Track::Battle),
+            GameState::Settings if match_in_progress => Some(Track::Battle),
+            GameState::Settings => Some(Track::Menu),
+        }
+    }
+
+    /// Layers played in sync for this track.
+    /// Every layer after the first one only becomes audible with the [`MusicIntensity`].
+    fn layers(&self, audio_assets: &AudioAssets) -> Vec<Handle<AudioSource>> {
+        match self {
+            Track::Menu => vec![audio_assets.menu_theme.clone()],
+            Track::Battle => vec![
+                audio_assets.battle_theme.clone(),
+                audio_assets.battle_intense.clone(),
+            ],
+        }
+    }
+}
+
+struct PlayingTrack {
+    track: Track,
+    layers: Vec<Handle<AudioInstance>>,
+    fade_in: Timer,
+}
+
+#[derive(Resource, Default)]
+struct MusicPlayer {
+    playing: Option<PlayingTrack>,
+}
+
+/// How intense the battle music is, from 0 (calm) to 1.
+#[derive(Resource, Default)]
+struct MusicIntensity(f32);
+
+/// Starts the track for the current state, fading out the previous one.
+fn switch_track(
+    state: Res<State<GameState>>,
+    match_entities: Query<(), With<MatchEntity>>,
+    mut music_player: ResMut<MusicPlayer>,
+    mut intensity: ResMut<MusicIntensity>,
+    audio_assets: Res<AudioAssets>,
+    music: Res<AudioChannel<Music>>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    let track = Track::for_state(state.get(), !match_entities.is_empty());
+    let current = music_player.playing.as_ref().map(|playing| playing.track);
+    if track == current {
+        return;
+    }
+
+    let fade = AudioTween::linear(Duration::from_secs_f32(CROSSFADE_SECONDS));
+    if let Some(previous) = music_player.playing.take() {
+        for handle in previous.layers {
+            if let Some(instance) = audio_instances.get_mut(&handle) {
+                instance.stop(fade.clone());
+            }
+        }
+    }
+    music_player.playing = track.map(|track| {
+        intensity.0 = 0.;
+        PlayingTrack {
+            track,
+            layers: track
+                .layers(&audio_assets)
+                .into_iter()
+                .map(|layer| music.play(layer).looped().with_volume(0.).handle())
+                .collect(),
+            fade_in: Timer::from_seconds(CROSSFADE_SECONDS, TimerMode::Once),
+        }
+    });
+}
+
+/// Ramps the intensity up the closer the two nearest ships are and when the round is about to end.
+#[allow(clippy::too_many_arguments)]
+fn update_intensity(
+    mut intensity: ResMut<MusicIntensity>,
+    ships: Query<&Transform, With<Player>>,
+    player_count: Res<PlayerCount>,
+    scores: Res<Scores>,
+    stocks: Res<Stocks>,
+    game_mode: Res<State<GameMode>>,
+    config: Res<MatchConfig>,
+    time: Res<Time>,
+) {
+    let closest_distance = ships
+        .iter_combinations()
+        .map(|[ship1, ship2]| ship1.translation.distance(ship2.translation))
+        .min_by(f32::total_cmp);
+    let mut target = closest_distance.map_or(0., |distance| {
+        1. - ((distance - CLOSE_DISTANCE) / (FAR_DISTANCE - CLOSE_DISTANCE)).clamp(0., 1.)
+    });
+    let final_duel = player_count.0 > 2 && ships.iter().count() == 2;
+    let match_point = match game_mode.get() {
+        GameMode::Dogfight => scores.match_point(config.rounds_to_win),
+        GameMode::Stock => stocks.last_life(player_count.0),
+        _ => false,
+    };
+    if final_duel || match_point {
+        target = target.max(ROUND_ENDING_INTENSITY);
+    }
+    let max_change = INTENSITY_RATE * time.delta_seconds();
+    intensity.0 += (target - intensity.0).clamp(-max_change, max_change);
+}
+
+fn update_music_volume(
+    mut music_player: ResMut<MusicPlayer>,
+    intensity: Res<MusicIntensity>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    // Virtual time is paused together with the match, but fades should keep going
+    time: Res<Time<Real>>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    let Some(playing) = music_player.playing.as_mut() else {
+        return;
+    };
+    playing.fade_in.tick(time.delta());
+    let mut volume = settings.audio.music() * playing.fade_in.percent() as f64;
+    if *state.get() != GameState::Playing && playing.track == Track::Battle {
+        volume *= PAUSED_VOLUME as f64;
+    }
+    for (index, handle) in playing.layers.iter().enumerate() {
+        let layer_volume = if index == 0 {
+            volume
+        } else {
+            volume * intensity.0 as f64
+        };
+        if let Some(instance) = audio_instances.get_mut(handle) {
+            instance.set_volume(layer_volume, AudioTween::default());
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::menu::ButtonColors;
+use crate::{despawn_match_entities, GameState, MatchEntity};
+use bevy::prelude::*;
+
+pub struct PausePlugin;
+
+/// This plugin lets the players pause a match with Escape or a gamepad's Start button.
+/// While in `GameState::Paused` no gameplay system runs and the pause menu is drawn
+/// on top of the frozen match.
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            toggle_pause.run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+        )
+        .add_systems(OnEnter(GameState::Paused), (setup_pause_menu, pause_time))
+        .add_systems(
+            Update,
+            click_pause_button.run_if(in_state(GameState::Paused)),
+        )
+        .add_systems(
+            OnExit(GameState::Paused),
+            (
+                cleanup_pause_menu,
+                // The match stays frozen while the settings are open
+                unpause_time.run_if(not(in_state(GameState::Settings))),
+            ),
+        );
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+impl PauseButton {
+    fn label(&self) -> &'static str {
+        match self {
+            PauseButton::Resume => "Resume",
+            PauseButton::Restart => "Restart",
+            PauseButton::Settings => "Settings",
+            PauseButton::Quit => "Quit to menu",
+        }
+    }
+}
+
+fn toggle_pause(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let start_pressed = gamepads.iter().any(|gamepad| {
+        gamepad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start))
+    });
+    if !keyboard_input.just_pressed(KeyCode::Escape) && !start_pressed {
+        return;
+    }
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+/// Stops the virtual clock so nothing relying on `Time` advances while paused.
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn setup_pause_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                ..default()
+            },
+            PauseMenu,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font_size: 60.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+            for button in [
+                PauseButton::Resume,
+                PauseButton::Restart,
+                PauseButton::Settings,
+                PauseButton::Quit,
+            ] {
+                let button_colors = ButtonColors::default();
+                children
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(260.0),
+                                height: Val::Px(50.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..Default::default()
+                        },
+                        button_colors,
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle {
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn click_pause_button(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    match_entities: Query<Entity, With<MatchEntity>>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            &PauseButton,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                PauseButton::Resume => next_state.set(GameState::Playing),
+                PauseButton::Restart => {
+                    // With the old match gone, entering `Playing` spawns a fresh one
+                    despawn_match_entities(&mut commands, &match_entities);
+                    next_state.set(GameState::Playing);
+                }
+                PauseButton::Settings => next_state.set(GameState::Settings),
+                PauseButton::Quit => {
+                    despawn_match_entities(&mut commands, &match_entities);
+                    next_state.set(GameState::Menu);
+                }
+            },
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+fn cleanup_pause_menu(mut commands: Commands, pause_menu: Query<Entity, With<PauseMenu>>) {
+    for entity in pause_menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,14 +1,24 @@
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide;
-use bevy::utils::HashMap;
 
-use crate::loading::TextureAssets;
-use crate::GameState;
+pub mod barnes_hut;
+pub mod portals;
+pub mod sources;
+
+use barnes_hut::{Body, QuadTree};
+
+use crate::match_setup::MatchConfig;
+use crate::player::Projectile;
+use crate::teams::Team;
+use crate::{GameState, Settings};
 
 #[derive(Component)]
 pub struct Collider {
     pub dimensions: Vec2,
     pub destroyable: bool,
+    /// How much of their speed bodies keep when bouncing off each other, from 0 to 1.
+    /// Two colliders bounce only if they both have one, otherwise they crash.
+    pub restitution: Option<f32>,
 }
 
 #[derive(Bundle, Default)]
@@ -19,8 +29,97 @@ pub struct PhysicsBundle {
     pub mass: Mass,
 }
 
-#[derive(Component, Debug, Default)]
-pub struct Forces(pub HashMap<String, Vec3>);
+/// What a force comes from. Forces are kept apart by source so they can be told apart
+/// when debugging, while still being summed up without allocating anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForceSource {
+    Gravity,
+    Thrust,
+    ReverseThrust,
+    Strafe,
+    FlightAssist,
+    /// Turns the body rather than pushing it, as a torque around the z axis.
+    RotationDamping,
+    Shockwave,
+    SolarWind,
+}
+
+impl ForceSource {
+    pub const ALL: [ForceSource; 8] = [
+        ForceSource::Gravity,
+        ForceSource::Thrust,
+        ForceSource::ReverseThrust,
+        ForceSource::Strafe,
+        ForceSource::FlightAssist,
+        ForceSource::RotationDamping,
+        ForceSource::Shockwave,
+        ForceSource::SolarWind,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ForceSource::Gravity => "gravity",
+            ForceSource::Thrust => "thrust",
+            ForceSource::ReverseThrust => "reverse thrust",
+            ForceSource::Strafe => "strafe",
+            ForceSource::FlightAssist => "flight assist",
+            ForceSource::RotationDamping => "rotation damping",
+            ForceSource::Shockwave => "shockwave",
+            ForceSource::SolarWind => "solar wind",
+        }
+    }
+
+    /// Whether the source turns bodies instead of pushing them, see [`Forces::torque`].
+    pub fn is_torque(&self) -> bool {
+        matches!(self, ForceSource::RotationDamping)
+    }
+}
+
+/// Forces applied to a body during the current step, one per [`ForceSource`].
+/// They're cleared at the start of every step, so sources have to apply them every frame.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Forces([Vec3; ForceSource::ALL.len()]);
+
+impl Forces {
+    pub fn set(&mut self, source: ForceSource, force: Vec3) {
+        self.0[source as usize] = force;
+    }
+
+    pub fn add(&mut self, source: ForceSource, force: Vec3) {
+        self.0[source as usize] += force;
+    }
+
+    pub fn get(&self, source: ForceSource) -> Vec3 {
+        self.0[source as usize]
+    }
+
+    /// Every source with the force it applies, including the ones applying none.
+    pub fn iter(&self) -> impl Iterator<Item = (ForceSource, Vec3)> + '_ {
+        ForceSource::ALL
+            .into_iter()
+            .map(|source| (source, self.get(source)))
+    }
+
+    /// The sum of all forces pushing the body, torques are left out.
+    pub fn net(&self) -> Vec3 {
+        self.iter()
+            .filter(|(source, _)| !source.is_torque())
+            .map(|(_, force)| force)
+            .sum()
+    }
+
+    /// The sum of all torques, turning the body counterclockwise.
+    pub fn torque(&self) -> f32 {
+        self.iter()
+            .filter(|(source, _)| source.is_torque())
+            .map(|(_, torque)| torque.z)
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.0 = default();
+    }
+}
 
 #[derive(Component, Default)]
 pub struct Acceleration(pub Vec3);
@@ -37,96 +136,346 @@ impl Default for Mass {
     }
 }
 
+/// Sent whenever two colliders crash into each other.
+#[derive(Event)]
+pub struct CollisionEvent {
+    pub entities: (Entity, Entity),
+    pub position: Vec3,
+}
+
+/// Sent whenever two colliders bounce off each other.
+#[derive(Event)]
+pub struct ImpactEvent {
+    pub entities: (Entity, Entity),
+    pub position: Vec3,
+    /// Kinetic energy of the bodies moving towards each other, right before the impact.
+    pub energy: f32,
+    /// Impulse the second body got, the first one got the opposite.
+    pub impulse: Vec3,
+}
+
+/// Keeps a body from crashing into other destroyable bodies, like ships and projectiles,
+/// it goes right through them instead. It still crashes into stars and obstacles,
+/// and bounces off what it can bounce off.
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
 // Objects so massive that they attract other objects with their gravity.
+// Repulsors are stars too, with a negative mass pushing everything away.
 #[derive(Component)]
 pub struct Star;
 
+/// Which bodies attract each other.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GravityMode {
+    /// Only stars attract, everything else just falls towards them.
+    #[default]
+    Stars,
+    /// Every body with a mass attracts every other, approximated with [`barnes_hut`].
+    /// Only static stars, the ones without [`Forces`], stay where they are.
+    NBody,
+}
+
+impl GravityMode {
+    pub fn cycle(&mut self) {
+        *self = match self {
+            GravityMode::Stars => GravityMode::NBody,
+            GravityMode::NBody => GravityMode::Stars,
+        };
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GravityMode::Stars => "Stars",
+            GravityMode::NBody => "Everything",
+        }
+    }
+}
+
 pub struct PhysicsPlugin;
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_star)
+        app.add_event::<CollisionEvent>()
+            .add_event::<ImpactEvent>()
+            .init_resource::<GravityMode>()
+            .add_systems(First, clear_forces.run_if(in_state(GameState::Playing)))
             .add_systems(
                 Update,
                 (
                     apply_gravity,
+                    sources::apply_shockwaves,
+                    sources::apply_solar_wind,
                     apply_forces,
-                    apply_acceleration,
-                    apply_velocity,
+                    apply_motion,
+                    portals::teleport_bodies,
+                    sources::swallow_bodies,
+                    bounce_bodies,
                     check_for_collisions,
+                    absorb_impacts,
                 )
+                    .chain()
                     .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                sources::draw_shockwaves
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
             );
     }
 }
 
-fn spawn_star(mut commands: Commands, textures: Res<TextureAssets>) {
-    commands.spawn((
-        SpriteBundle {
-            texture: textures.bevy.clone(),
-            transform: Transform::from_translation(Vec3::new(0., 0., 0.))
-                .with_scale(Vec3::new(0.5, 0.5, 0.5)),
-            ..default()
-        },
-        Mass(10_000.),
-        Collider {
-            // 128 is half the image used because of scale. We also give some 32px of extra space.
-            dimensions: Vec2::new(96., 96.),
-            destroyable: false, // Should never destroy a star
-        },
-        Star,
-    ));
+fn clear_forces(mut query: Query<&mut Forces>) {
+    for mut forces in query.iter_mut() {
+        forces.clear();
+    }
 }
 
 /// Sum all forces being applied to entities, in order to get the net force.
 /// Then, modify the acceleration according to Newton's 2nd law.
-fn apply_forces(mut query: Query<(&Forces, &Mass, &mut Acceleration)>) {
+/// Systems applying forces have to run before this one.
+pub fn apply_forces(mut query: Query<(&Forces, &Mass, &mut Acceleration)>) {
     for (forces, mass, mut acceleration) in query.iter_mut() {
         // F = m * a -> a = F / m
-        acceleration.0 = forces.0.values().sum::<Vec3>() / mass.0;
+        acceleration.0 = forces.net() / mass.0;
     }
 }
 
-/// Change entities' velocity according to their acceleration.
-fn apply_acceleration(mut query: Query<(&Acceleration, &mut Velocity)>, time: Res<Time>) {
-    for (acceleration, mut velocity) in query.iter_mut() {
-        velocity.0 += acceleration.0 * time.delta_seconds();
+/// Change entities' velocity according to their acceleration, then their position
+/// according to their velocity.
+fn apply_motion(mut query: Query<(&Acceleration, &mut Velocity, &mut Transform)>, time: Res<Time>) {
+    for (acceleration, mut velocity, mut transform) in query.iter_mut() {
+        integrate(
+            &mut transform.translation,
+            &mut velocity.0,
+            acceleration.0,
+            time.delta_seconds(),
+        );
     }
 }
 
-/// Change entities' positions according to their velocity.
-fn apply_velocity(mut query: Query<(&Velocity, &mut Transform)>, time: Res<Time>) {
-    for (velocity, mut transform) in query.iter_mut() {
-        transform.translation += velocity.0 * time.delta_seconds();
-    }
+/// Moves a body forward in time by `delta_seconds`, with semi-implicit Euler integration.
+/// Anything predicting motion has to use this too, to match what actually happens.
+pub fn integrate(position: &mut Vec3, velocity: &mut Vec3, acceleration: Vec3, delta_seconds: f32) {
+    *velocity += acceleration * delta_seconds;
+    *position += *velocity * delta_seconds;
 }
 
 /// Apply a gravitational force from entities with the `Star` component
-/// to those that don't have it.
+/// to everything else, or from every body to every other one, see [`GravityMode`].
+/// Dynamic stars, the ones with [`Forces`], are pulled by everything they pull.
+/// Every pull is scaled by the gravity strength of the match.
 fn apply_gravity(
-    mut objects_query: Query<(&Transform, &mut Forces), Without<Star>>,
-    stars_query: Query<(&Mass, &Transform), With<Star>>,
+    gravity_mode: Res<GravityMode>,
+    config: Res<MatchConfig>,
+    mut objects_query: Query<(Entity, &Transform, &mut Forces, Option<&Mass>, Has<Star>)>,
+    stars_query: Query<(Entity, &Mass, &Transform), With<Star>>,
+    bodies_query: Query<(&Mass, &Transform)>,
 ) {
-    for (transform, mut forces) in objects_query.iter_mut() {
-        for (star_mass, star_transform) in stars_query.iter() {
-            // F = G * m1 * m2 / r^2
-            // G is too small to account for. m2 as well.
-            let direction_to_star = star_transform.translation - transform.translation;
-            let distance_to_star = direction_to_star.length();
-            let gravitational_force = star_mass.0 / distance_to_star.powi(2);
-            forces.0.insert(
-                "gravity".to_string(),
-                direction_to_star * gravitational_force,
-            );
+    match *gravity_mode {
+        GravityMode::Stars => {
+            let stars: Vec<(Entity, f32, Vec3)> = stars_query
+                .iter()
+                .map(|(star, mass, transform)| (star, mass.0, transform.translation))
+                .collect();
+            // Stars pull each other both ways already, only the rest pulls them back
+            let pulled: Vec<(f32, Vec3)> = objects_query
+                .iter()
+                .filter(|(_, _, _, _, is_star)| !is_star)
+                .map(|(_, transform, _, mass, _)| {
+                    (mass.map_or(1., |mass| mass.0), transform.translation)
+                })
+                .collect();
+            for (entity, transform, mut forces, mass, is_star) in objects_query.iter_mut() {
+                let mass = mass.map_or(1., |mass| mass.0);
+                let other_stars = stars
+                    .iter()
+                    .filter(|(star, _, _)| *star != entity)
+                    .map(|(_, star_mass, position)| (*star_mass, *position));
+                let mut force = gravity(transform.translation, mass, other_stars);
+                if is_star {
+                    force += gravity(transform.translation, mass, pulled.iter().copied());
+                }
+                forces.set(ForceSource::Gravity, force * config.gravity);
+            }
+        }
+        GravityMode::NBody => {
+            let (bodies, repulsors): (Vec<Body>, Vec<Body>) = bodies_query
+                .iter()
+                .map(|(mass, transform)| Body {
+                    position: transform.translation.truncate(),
+                    mass: mass.0,
+                })
+                .partition(|body| body.mass >= 0.);
+            // Negative masses would throw off the centers of mass in the tree,
+            // there are few repulsors anyway
+            let tree = QuadTree::new(&bodies);
+            for (_, transform, mut forces, mass, _) in objects_query.iter_mut() {
+                let position = transform.translation.truncate();
+                let force = (tree.gravity(position, barnes_hut::THETA)
+                    + barnes_hut::exact_gravity(&repulsors, position))
+                    * mass.map_or(1., |mass| mass.0);
+                forces.set(ForceSource::Gravity, force.extend(0.) * config.gravity);
+            }
         }
     }
 }
 
-fn check_for_collisions(mut commands: Commands, query: Query<(Entity, &Transform, &Collider)>) {
+/// Sum of the gravitational forces of `stars`, given as mass and position,
+/// on a body of `mass` at `position`.
+pub fn gravity(position: Vec3, mass: f32, stars: impl Iterator<Item = (f32, Vec3)>) -> Vec3 {
+    stars
+        .map(|(star_mass, star_position)| {
+            gravitational_force(star_mass, mass, star_position - position)
+        })
+        .sum()
+}
+
+/// Force pulling a body of `mass` towards a star `direction_to_star` away from it.
+/// The star is pulled back just as hard, so momentum is conserved.
+pub fn gravitational_force(star_mass: f32, mass: f32, direction_to_star: Vec3) -> Vec3 {
+    // F = G * m1 * m2 / r^2
+    // G is too small to account for, star masses make up for it.
+    direction_to_star * star_mass * mass / direction_to_star.length_squared()
+}
+
+/// Speed a body of `mass` needs to stay in a circular orbit `distance` away from a star.
+pub fn circular_orbit_speed(star_mass: f32, mass: f32, distance: f32) -> f32 {
+    // The gravity has to provide the centripetal force: F = m * v^2 / r
+    let force = gravitational_force(star_mass, mass, Vec3::X * distance).length();
+    (force * distance / mass).sqrt()
+}
+
+/// Overlap allowed between bouncing colliders, so resting bodies don't jitter.
+const PENETRATION_SLOP: f32 = 0.5;
+/// How much of the overlap is corrected every step.
+const POSITION_CORRECTION: f32 = 0.8;
+
+/// Direction from the first collider to the second along the axis they overlap the least on,
+/// and by how much they overlap. `None` if they don't touch.
+fn contact(position1: Vec2, size1: Vec2, position2: Vec2, size2: Vec2) -> Option<(Vec2, f32)> {
+    let offset = position2 - position1;
+    let overlap = (size1 + size2) / 2. - offset.abs();
+    if overlap.cmple(Vec2::ZERO).any() {
+        return None;
+    }
+    Some(if overlap.x < overlap.y {
+        (Vec2::new(offset.x.signum(), 0.), overlap.x)
+    } else {
+        (Vec2::new(0., offset.y.signum()), overlap.y)
+    })
+}
+
+/// Bounces colliders that both have a restitution off each other, conserving their momentum,
+/// and pushes them apart so they don't overlap. Colliders without a [`Velocity`] and [`Mass`]
+/// don't move, as if they were infinitely heavy.
+/// The restitution of the bouncier collider is used.
+pub fn bounce_bodies(
+    mut impact_events: EventWriter<ImpactEvent>,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &Collider,
+        Option<&mut Velocity>,
+        Option<&Mass>,
+    )>,
+) {
+    let mut iter = query.iter_combinations_mut();
+    while let Some(
+        [(entity1, mut transform1, collider1, velocity1, mass1), (entity2, mut transform2, collider2, velocity2, mass2)],
+    ) = iter.fetch_next()
+    {
+        let (Some(restitution1), Some(restitution2)) =
+            (collider1.restitution, collider2.restitution)
+        else {
+            continue;
+        };
+        let Some((normal, penetration)) = contact(
+            transform1.translation.truncate(),
+            collider1.dimensions,
+            transform2.translation.truncate(),
+            collider2.dimensions,
+        ) else {
+            continue;
+        };
+        let normal = normal.extend(0.);
+        let inverse_mass = |velocity: &Option<Mut<Velocity>>, mass: Option<&Mass>| match mass {
+            Some(mass) if velocity.is_some() && mass.0 > 0. => 1. / mass.0,
+            _ => 0.,
+        };
+        let inverse_mass1 = inverse_mass(&velocity1, mass1);
+        let inverse_mass2 = inverse_mass(&velocity2, mass2);
+        let total_inverse_mass = inverse_mass1 + inverse_mass2;
+        if total_inverse_mass == 0. {
+            continue;
+        }
+
+        let correction = normal * (penetration - PENETRATION_SLOP).max(0.) * POSITION_CORRECTION
+            / total_inverse_mass;
+        transform1.translation -= correction * inverse_mass1;
+        transform2.translation += correction * inverse_mass2;
+
+        let velocity_of = |velocity: &Option<Mut<Velocity>>| {
+            velocity.as_ref().map_or(Vec3::ZERO, |velocity| velocity.0)
+        };
+        let closing_speed = (velocity_of(&velocity1) - velocity_of(&velocity2)).dot(normal);
+        // Bodies already moving apart only needed to be pushed out of each other
+        if closing_speed <= 0. {
+            continue;
+        }
+        let restitution = restitution1.max(restitution2);
+        let impulse = normal * (1. + restitution) * closing_speed / total_inverse_mass;
+        if let Some(mut velocity) = velocity1 {
+            velocity.0 -= impulse * inverse_mass1;
+        }
+        if let Some(mut velocity) = velocity2 {
+            velocity.0 += impulse * inverse_mass2;
+        }
+        impact_events.send(ImpactEvent {
+            entities: (entity1, entity2),
+            position: (transform1.translation + transform2.translation) / 2.,
+            energy: closing_speed * closing_speed / total_inverse_mass / 2.,
+            impulse,
+        });
+    }
+}
+
+/// Destroys destroyable colliders that touch anything else, unless they bounce off it.
+/// The despawns are deferred, so systems reacting to [`CollisionEvent`]s can still query
+/// the entities involved as long as they run after this one.
+/// Without friendly fire, projectiles go through everything on the team that fired them.
+pub fn check_for_collisions(
+    mut commands: Commands,
+    mut collision_events: EventWriter<CollisionEvent>,
+    query: Query<(
+        Entity,
+        &Transform,
+        &Collider,
+        Option<&Team>,
+        Has<Projectile>,
+        Has<Invulnerable>,
+    )>,
+    settings: Res<Settings>,
+) {
     let mut iter = query.iter_combinations();
 
-    while let Some([(entity1, transform1, collider1), (entity2, transform2, collider2)]) =
-        iter.fetch_next()
+    while let Some(
+        [(entity1, transform1, collider1, team1, projectile1, invulnerable1), (entity2, transform2, collider2, team2, projectile2, invulnerable2)],
+    ) = iter.fetch_next()
     {
+        // Nothing happens when indestructible things, like stars and obstacles, touch
+        if !collider1.destroyable && !collider2.destroyable {
+            continue;
+        }
+        if (invulnerable1 || invulnerable2) && collider1.destroyable && collider2.destroyable {
+            continue;
+        }
+        if collider1.restitution.is_some() && collider2.restitution.is_some() {
+            continue;
+        }
+        let teammates = team1.is_some() && team1 == team2;
+        if teammates && (projectile1 || projectile2) && !settings.gameplay.friendly_fire {
+            continue;
+        }
         let collision = collide(
             transform1.translation,
             collider1.dimensions,
@@ -134,6 +483,10 @@ fn check_for_collisions(mut commands: Commands, query: Query<(Entity, &Transform
             collider2.dimensions,
         );
         if collision.is_some() {
+            collision_events.send(CollisionEvent {
+                entities: (entity1, entity2),
+                position: (transform1.translation + transform2.translation) / 2.,
+            });
             if collider1.destroyable {
                 commands.entity(entity1).despawn();
             }
@@ -143,3 +496,24 @@ fn check_for_collisions(mut commands: Commands, query: Query<(Entity, &Transform
         }
     }
 }
+
+/// Dynamic stars take the momentum of the bodies crashing into them.
+fn absorb_impacts(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut stars_query: Query<(&Mass, &mut Velocity), With<Star>>,
+    bodies_query: Query<(&Mass, &Velocity), Without<Star>>,
+) {
+    for event in collision_events.read() {
+        let (first, second) = event.entities;
+        for (star, body) in [(first, second), (second, first)] {
+            let (Ok((star_mass, mut star_velocity)), Ok((body_mass, body_velocity))) =
+                (stars_query.get_mut(star), bodies_query.get(body))
+            else {
+                continue;
+            };
+            // The body is destroyed, so the collision is perfectly inelastic
+            star_velocity.0 = (star_velocity.0 * star_mass.0 + body_velocity.0 * body_mass.0)
+                / (star_mass.0 + body_mass.0);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Approximates the gravity between many bodies with a Barnes-Hut quadtree:
+//! bodies far enough away are grouped together and attract as a single body placed
+//! at their center of mass, which brings the cost down from O(n²) to O(n log n).
+
+use super::gravitational_force;
+use bevy::prelude::*;
+
+/// How far a group of bodies has to be, relative to the size of the area they're in,
+/// to be treated as a single body. Lower is more accurate and slower, 0 is exact.
+pub const THETA: f32 = 0.5;
+/// Bodies closer than this don't attract each other, so a body doesn't attract itself
+/// and overlapping bodies don't get flung away.
+pub const MIN_DISTANCE: f32 = 1.;
+/// Past this depth, bodies are grouped together no matter how close they are,
+/// so bodies at the same position don't split the tree forever.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Body {
+    pub position: Vec2,
+    pub mass: f32,
+}
+
+/// Force `other` pulls a unit of mass at `position` with, following the same law as
+/// [`gravitational_force`]. Bodies are pulled in proportion to their mass.
+pub fn attraction(position: Vec2, other: Body) -> Vec2 {
+    let direction = other.position - position;
+    if direction.length_squared() < MIN_DISTANCE * MIN_DISTANCE {
+        return Vec2::ZERO;
+    }
+    gravitational_force(other.mass, 1., direction.extend(0.)).truncate()
+}
+
+/// The exact gravity of `bodies` on a unit of mass at `position`, summing up every one of them.
+pub fn exact_gravity(bodies: &[Body], position: Vec2) -> Vec2 {
+    bodies.iter().map(|body| attraction(position, *body)).sum()
+}
+
+struct Node {
+    /// All the bodies in this node, as a single body at their center of mass.
+    body: Body,
+    /// Width of the square area the node covers.
+    size: f32,
+    /// Index of the first of four consecutive children, if the node was split.
+    children: Option<usize>,
+}
+
+pub struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    pub fn new(bodies: &[Body]) -> Self {
+        let mut tree = QuadTree { nodes: vec![] };
+        if bodies.is_empty() {
+            return tree;
+        }
+        let (min, max) = bodies.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), body| (min.min(body.position), max.max(body.position)),
+        );
+        let size = (max - min).max_element().max(MIN_DISTANCE);
+        let mut bodies = bodies.to_vec();
+        tree.nodes.push(group(&bodies, size));
+        tree.split(0, &mut bodies, (min + max) / 2., 0);
+        tree
+    }
+
+    /// Splits `node` into four quadrants around `center`, as long as it has more than one body.
+    fn split(&mut self, node: usize, bodies: &mut [Body], center: Vec2, depth: usize) {
+        if bodies.len() <= 1 || depth >= MAX_DEPTH {
+            return;
+        }
+        let size = self.nodes[node].size / 2.;
+        // Sort the bodies by quadrant: left before right, then bottom before top
+        let right = partition(bodies, |body| body.position.x < center.x);
+        let (left_bodies, right_bodies) = bodies.split_at_mut(right);
+        let left_top = partition(left_bodies, |body| body.position.y < center.y);
+        let right_top = partition(right_bodies, |body| body.position.y < center.y);
+        let (bottom_left, top_left) = left_bodies.split_at_mut(left_top);
+        let (bottom_right, top_right) = right_bodies.split_at_mut(right_top);
+
+        let first_child = self.nodes.len();
+        self.nodes[node].children = Some(first_child);
+        let quadrants = [
+            (bottom_left, Vec2::new(-1., -1.)),
+            (top_left, Vec2::new(-1., 1.)),
+            (bottom_right, Vec2::new(1., -1.)),
+            (top_right, Vec2::new(1., 1.)),
+        ];
+        for (quadrant_bodies, _) in &quadrants {
+            self.nodes.push(group(quadrant_bodies, size));
+        }
+        for (index, (quadrant_bodies, direction)) in quadrants.into_iter().enumerate() {
+            let quadrant_center = center + direction * size / 2.;
+            self.split(
+                first_child + index,
+                quadrant_bodies,
+                quadrant_center,
+                depth + 1,
+            );
+        }
+    }
+
+    /// The gravity of all bodies in the tree on a unit of mass at `position`.
+    pub fn gravity(&self, position: Vec2, theta: f32) -> Vec2 {
+        if self.nodes.is_empty() {
+            return Vec2::ZERO;
+        }
+        self.node_gravity(0, position, theta)
+    }
+
+    fn node_gravity(&self, node: usize, position: Vec2, theta: f32) -> Vec2 {
+        let Node {
+            body,
+            size,
+            children,
+        } = &self.nodes[node];
+        if body.mass == 0. {
+            return Vec2::ZERO;
+        }
+        match children {
+            Some(first_child) if *size > theta * body.position.distance(position) => (0..4)
+                .map(|child| self.node_gravity(first_child + child, position, theta))
+                .sum(),
+            _ => attraction(position, *body),
+        }
+    }
+}
+
+/// A node holding `bodies`, which may be none.
+fn group(bodies: &[Body], size: f32) -> Node {
+    let mass: f32 = bodies.iter().map(|body| body.mass).sum();
+    let position = if mass > 0. {
+        bodies
+            .iter()
+            .map(|body| body.position * body.mass)
+            .sum::<Vec2>()
+            / mass
+    } else {
+        Vec2::ZERO
+    };
+    Node {
+        body: Body { position, mass },
+        size,
+        children: None,
+    }
+}
+
+/// Moves the bodies matching `first` before the others, returning how many matched.
+fn partition(bodies: &mut [Body], first: impl Fn(&Body) -> bool) -> usize {
+    let mut matched = 0;
+    for index in 0..bodies.len() {
+        if first(&bodies[index]) {
+            bodies.swap(matched, index);
+            matched += 1;
+        }
+    }
+    matched
+}
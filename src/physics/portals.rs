@@ -0,0 +1,91 @@
+//! Wormholes: pairs of portals sending whatever crosses one of them out of the other.
+//! Only bodies go through, stars keep pulling in a straight line as if there was no portal.
+
+use super::{Star, Velocity};
+use bevy::prelude::*;
+
+/// One end of a wormhole.
+#[derive(Component)]
+pub struct Portal {
+    pub partner: Entity,
+    pub radius: f32,
+    /// Radians bodies and their velocities are turned by when coming out of the partner.
+    pub rotation: f32,
+}
+
+/// Keeps a body that just came out of a portal from going straight back in.
+/// It lasts at least [`IMMUNITY_SECONDS`], and until the body is clear of the portal.
+#[derive(Component)]
+pub struct Teleported {
+    exit: Entity,
+    timer: Timer,
+}
+
+const IMMUNITY_SECONDS: f32 = 0.5;
+
+/// Where a body crossing a portal at `entrance` comes out of the one at `exit`,
+/// and with which velocity. It keeps its offset from the portal's center, turned like its velocity.
+pub fn pass_through(
+    portal: &Portal,
+    entrance: Vec3,
+    exit: Vec3,
+    position: Vec3,
+    velocity: Vec3,
+) -> (Vec3, Vec3) {
+    let rotation = Quat::from_rotation_z(portal.rotation);
+    (exit + rotation * (position - entrance), rotation * velocity)
+}
+
+/// Sends bodies inside a portal out of its partner, unless they just came out of one.
+pub fn teleport_bodies(
+    mut commands: Commands,
+    portals_query: Query<(&Portal, &Transform), Without<Velocity>>,
+    mut bodies_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            Option<&mut Teleported>,
+        ),
+        Without<Star>,
+    >,
+    time: Res<Time>,
+) {
+    for (body, mut transform, mut velocity, teleported) in bodies_query.iter_mut() {
+        if let Some(mut teleported) = teleported {
+            let clear =
+                portals_query
+                    .get(teleported.exit)
+                    .map_or(true, |(portal, portal_transform)| {
+                        transform.translation.distance(portal_transform.translation) > portal.radius
+                    });
+            if teleported.timer.tick(time.delta()).finished() && clear {
+                commands.entity(body).remove::<Teleported>();
+            }
+            continue;
+        }
+        let entrance = portals_query.iter().find(|(portal, portal_transform)| {
+            transform.translation.distance(portal_transform.translation) <= portal.radius
+        });
+        let Some((portal, entrance_transform)) = entrance else {
+            continue;
+        };
+        let Ok((_, exit_transform)) = portals_query.get(portal.partner) else {
+            continue;
+        };
+        let (position, new_velocity) = pass_through(
+            portal,
+            entrance_transform.translation,
+            exit_transform.translation,
+            transform.translation,
+            velocity.0,
+        );
+        transform.translation = position;
+        transform.rotate_z(portal.rotation);
+        velocity.0 = new_velocity;
+        commands.entity(body).insert(Teleported {
+            exit: portal.partner,
+            timer: Timer::from_seconds(IMMUNITY_SECONDS, TimerMode::Once),
+        });
+    }
+}
@@ -0,0 +1,176 @@
+//! Stars that do more than pull: black holes swallowing what crosses their event horizon,
+//! pulsars sending out shockwaves and stars blowing solar wind.
+
+use super::{Collider, CollisionEvent, ForceSource, Forces, Invulnerable, Star};
+use crate::player::Player;
+use bevy::prelude::*;
+
+/// Swallows anything destroyable closer than this to the star, usually a black hole.
+#[derive(Component)]
+pub struct EventHorizon(pub f32);
+
+/// Sends out a shockwave every time the timer finishes, pushing away everything it passes.
+#[derive(Component)]
+pub struct Pulsar {
+    pub timer: Timer,
+    pub strength: f32,
+    /// Radius of every shockwave still travelling.
+    pub shockwaves: Vec<f32>,
+}
+
+impl Pulsar {
+    pub fn new(period: f32, strength: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(period, TimerMode::Repeating),
+            strength,
+            shockwaves: vec![],
+        }
+    }
+}
+
+/// Pushes ships away from the star, as long as nothing stands between them.
+/// It weakens with distance just like gravity.
+#[derive(Component)]
+pub struct SolarWind(pub f32);
+
+/// Pixels per second.
+const SHOCKWAVE_SPEED: f32 = 400.;
+const SHOCKWAVE_WIDTH: f32 = 40.;
+/// Shockwaves fade out once they're this big, past the corners of the largest arenas.
+const SHOCKWAVE_RANGE: f32 = 1800.;
+const SHOCKWAVE_COLOR: Color = Color::rgba(0.5, 0.9, 1., 0.5);
+/// How many rays are cast from the star across a ship to tell how exposed it is.
+const SOLAR_WIND_RAYS: usize = 5;
+
+/// Despawns whatever crosses an event horizon, as if it had crashed into the star.
+/// Invulnerable bodies make it across, but still crash if they reach the star itself.
+pub fn swallow_bodies(
+    mut commands: Commands,
+    mut collision_events: EventWriter<CollisionEvent>,
+    holes_query: Query<(Entity, &Transform, &EventHorizon)>,
+    bodies_query: Query<(Entity, &Transform, &Collider), (Without<Star>, Without<Invulnerable>)>,
+) {
+    for (hole, hole_transform, event_horizon) in holes_query.iter() {
+        for (body, transform, collider) in bodies_query.iter() {
+            if !collider.destroyable
+                || transform.translation.distance(hole_transform.translation) > event_horizon.0
+            {
+                continue;
+            }
+            collision_events.send(CollisionEvent {
+                entities: (hole, body),
+                position: transform.translation,
+            });
+            commands.entity(body).despawn();
+        }
+    }
+}
+
+/// Sends out new shockwaves, moves them along and pushes what they pass through.
+pub fn apply_shockwaves(
+    mut pulsars_query: Query<(&Transform, &mut Pulsar)>,
+    mut objects_query: Query<(&Transform, &mut Forces), Without<Star>>,
+    time: Res<Time>,
+) {
+    for (pulsar_transform, mut pulsar) in pulsars_query.iter_mut() {
+        if pulsar.timer.tick(time.delta()).just_finished() {
+            pulsar.shockwaves.push(0.);
+        }
+        let travelled = SHOCKWAVE_SPEED * time.delta_seconds();
+        pulsar.shockwaves.retain_mut(|radius| {
+            *radius += travelled;
+            *radius < SHOCKWAVE_RANGE
+        });
+        for (transform, mut forces) in objects_query.iter_mut() {
+            let away = transform.translation - pulsar_transform.translation;
+            let distance = away.length();
+            let hit = pulsar
+                .shockwaves
+                .iter()
+                .any(|radius| (distance - radius).abs() < SHOCKWAVE_WIDTH / 2.);
+            if hit && distance > 0. {
+                forces.add(ForceSource::Shockwave, away / distance * pulsar.strength);
+            }
+        }
+    }
+}
+
+pub fn draw_shockwaves(mut gizmos: Gizmos, pulsars_query: Query<(&Transform, &Pulsar)>) {
+    for (transform, pulsar) in pulsars_query.iter() {
+        for radius in &pulsar.shockwaves {
+            let fade = 1. - radius / SHOCKWAVE_RANGE;
+            gizmos.circle_2d(
+                transform.translation.truncate(),
+                *radius,
+                SHOCKWAVE_COLOR.with_a(SHOCKWAVE_COLOR.a() * fade),
+            );
+        }
+    }
+}
+
+/// Pushes ships away from stars with solar wind, in proportion to how much of the ship
+/// the star can see past obstacles and other stars.
+pub fn apply_solar_wind(
+    winds_query: Query<(Entity, &Transform, &SolarWind)>,
+    mut ships_query: Query<(&Transform, &Collider, &mut Forces), With<Player>>,
+    blockers_query: Query<(Entity, &Transform, &Collider), Without<Player>>,
+) {
+    for (star, star_transform, wind) in winds_query.iter() {
+        let origin = star_transform.translation.truncate();
+        let blockers: Vec<(Vec2, Vec2)> = blockers_query
+            .iter()
+            .filter(|(entity, _, collider)| *entity != star && !collider.destroyable)
+            .map(|(_, transform, collider)| {
+                (transform.translation.truncate(), collider.dimensions / 2.)
+            })
+            .collect();
+        for (transform, collider, mut forces) in ships_query.iter_mut() {
+            let away = transform.translation.truncate() - origin;
+            let distance = away.length();
+            if distance == 0. {
+                continue;
+            }
+            let across = away.perp() / distance * collider.dimensions.max_element();
+            let exposed = (0..SOLAR_WIND_RAYS)
+                .map(|ray| {
+                    let offset = ray as f32 / (SOLAR_WIND_RAYS - 1) as f32 - 0.5;
+                    origin + away + across * offset
+                })
+                .filter(|target| {
+                    !blockers.iter().any(|(center, half_size)| {
+                        segment_hits_box(origin, *target, *center, *half_size)
+                    })
+                })
+                .count();
+            let exposure = exposed as f32 / SOLAR_WIND_RAYS as f32;
+            let force = away / distance * wind.0 * exposure / distance;
+            forces.add(ForceSource::SolarWind, force.extend(0.));
+        }
+    }
+}
+
+/// Whether the segment from `start` to `end` crosses an axis aligned box.
+fn segment_hits_box(start: Vec2, end: Vec2, center: Vec2, half_size: Vec2) -> bool {
+    let direction = end - start;
+    let (mut enter, mut exit) = (0f32, 1f32);
+    for axis in 0..2 {
+        let (min, max) = (
+            center[axis] - half_size[axis],
+            center[axis] + half_size[axis],
+        );
+        if direction[axis] == 0. {
+            if start[axis] < min || start[axis] > max {
+                return false;
+            }
+            continue;
+        }
+        let first = (min - start[axis]) / direction[axis];
+        let second = (max - start[axis]) / direction[axis];
+        enter = enter.max(first.min(second));
+        exit = exit.min(first.max(second));
+        if enter > exit {
+            return false;
+        }
+    }
+    true
+}
@@ -1,122 +1,767 @@
 use crate::actions::Actions;
-use crate::loading::TextureAssets;
-use crate::physics::{Collider, Forces, Mass, PhysicsBundle, Velocity};
-use crate::GameState;
+use crate::level::{Level, SelectedLevel, SpawnPlacement, SpawnPoint};
+use crate::match_setup::MatchConfig;
+use crate::physics::{
+    apply_forces, bounce_bodies, check_for_collisions, circular_orbit_speed, gravitational_force,
+    Collider, CollisionEvent, ForceSource, Forces, ImpactEvent, Invulnerable, Mass, PhysicsBundle,
+    Star, Velocity,
+};
+use crate::rng::{GameRng, RngStream};
+use crate::ship::{FuelTank, SelectedShips, ShipClass, Weapon, WeaponSet};
+use crate::teams::{Team, TeamMode};
+use crate::{GameState, MatchEntity, Settings, SpawnMatch};
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 use bevy::sprite::MaterialMesh2dBundle;
 
 pub struct PlayerPlugin;
 
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 4;
+
+/// Colors of the players in menus, and of their ships in free-for-all matches.
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::rgb(1.0, 0.35, 0.3),
+    Color::rgb(0.35, 0.6, 1.0),
+    Color::rgb(0.4, 0.9, 0.4),
+    Color::rgb(1.0, 0.85, 0.3),
+];
+
+/// How many players take part in the next match.
+#[derive(Resource)]
+pub struct PlayerCount(pub usize);
+
+impl Default for PlayerCount {
+    fn default() -> Self {
+        Self(MIN_PLAYERS)
+    }
+}
+
+impl PlayerCount {
+    /// Adds a player, going back to the minimum after the maximum.
+    pub fn cycle(&mut self) {
+        self.0 = if self.0 >= MAX_PLAYERS {
+            MIN_PLAYERS
+        } else {
+            self.0 + 1
+        };
+    }
+}
+
+/// Which players fly with flight assist, picked on the ship select screen.
+/// Players have to opt in, so leaving it off for some of them works as a handicap.
+#[derive(Resource, Default)]
+pub struct FlightAssists(pub [bool; MAX_PLAYERS]);
+
 #[derive(Component)]
 pub struct Player {
-    number: u8,
+    pub number: u8,
+}
+
+impl Player {
+    /// Position of the player in per-player collections like [`Actions::player_actions`].
+    pub fn index(&self) -> usize {
+        (self.number - 1) as usize
+    }
+}
+
+pub fn player_color(index: usize) -> Color {
+    PLAYER_COLORS[index]
+}
+
+/// How hard and how fast a ship can thrust and turn, from its [`ShipClass`].
+#[derive(Component)]
+pub struct Engine {
+    thrust: f32,
+    reverse_thrust: f32,
+    strafe_thrust: Option<f32>,
+    turn_rate: f32,
+    /// Radians per second the ship tumbles after bouncing off something off center,
+    /// until its player steers.
+    spin: f32,
+    fuel: Option<Fuel>,
+    thrusting: bool,
 }
 
+impl Engine {
+    /// Whether any thruster is pushing the ship this frame.
+    /// Players can't thrust with an empty tank.
+    pub fn thrusting(&self) -> bool {
+        self.thrusting
+    }
+
+    /// Burns fuel for `throttle` times the main engine's thrust, or refills the tank
+    /// if nothing is burning. Returns whether there was fuel to burn.
+    fn burn(&mut self, throttle: f32, delta_seconds: f32) -> bool {
+        match self.fuel.as_mut() {
+            Some(fuel) if throttle > 0. && fuel.remaining > 0. => {
+                fuel.remaining = (fuel.remaining - throttle * delta_seconds).max(0.);
+                true
+            }
+            Some(fuel) => {
+                fuel.remaining = (fuel.remaining + fuel.tank.refill_rate * delta_seconds)
+                    .min(fuel.tank.capacity);
+                false
+            }
+            None => throttle > 0.,
+        }
+    }
+
+    /// Fills up the tank, if the ship has one.
+    pub fn refuel(&mut self) {
+        if let Some(fuel) = self.fuel.as_mut() {
+            fuel.remaining = fuel.tank.capacity;
+        }
+    }
+}
+
+/// Seconds ships can't crash into other ships or projectiles after spawning.
+const SPAWN_INVULNERABILITY: f32 = 2.;
+/// How often invulnerable ships blink, per second.
+const INVULNERABLE_BLINK_RATE: f32 = 8.;
+const INVULNERABLE_ALPHA: f32 = 0.35;
+/// Seconds the warp-in ring of a respawned ship takes to collapse.
+const WARP_IN_SECONDS: f32 = 0.5;
+/// Size of the warp-in ring when it appears, relative to the ship.
+const WARP_IN_SCALE: f32 = 3.;
+const WARP_IN_ALPHA: f32 = 0.6;
+
+/// Ships with flight assist fire their thrusters to hold a circular orbit
+/// while the player isn't thrusting, and to stop tumbling while the player isn't steering.
 #[derive(Component)]
-pub struct Gun {
+pub struct FlightAssist;
+
+/// How much of the main engine's thrust flight assist can use.
+const ASSIST_THRUST: f32 = 0.5;
+/// How quickly flight assist corrects the velocity, per second.
+const ASSIST_RESPONSE: f32 = 2.;
+/// Flight assist leaves velocities this close to the orbit alone, to save fuel.
+const ASSIST_TOLERANCE: f32 = 5.;
+
+struct Fuel {
+    tank: FuelTank,
+    /// Seconds of thrust left.
+    remaining: f32,
+}
+
+/// What's left of a ship's hull. Ships lose hull bouncing off things
+/// with collision damage on, and are destroyed once it's gone. Crashing empties it at once.
+#[derive(Component)]
+pub struct Hull(pub f32);
+
+impl Hull {
+    pub fn repair(&mut self) {
+        self.0 = FULL_HULL;
+    }
+}
+
+const FULL_HULL: f32 = 100.;
+/// Hull lost per unit of impact energy.
+/// Two fighters ramming each other head-on at 300 pixels per second are both destroyed.
+const DAMAGE_PER_ENERGY: f32 = 1. / 200.;
+
+/// The weapons of a ship, from its [`ShipClass`].
+#[derive(Component)]
+pub struct Guns(Vec<Gun>);
+
+struct Gun {
+    weapon: Weapon,
     cooldown_timer: Timer,
 }
 
+impl Guns {
+    pub fn weapons(&self) -> impl Iterator<Item = &Weapon> {
+        self.0.iter().map(|gun| &gun.weapon)
+    }
+}
+
+/// Guns reload twice as fast until the timer finishes.
+#[derive(Component)]
+pub struct RapidFire(pub Timer);
+
 #[derive(Component)]
 pub struct Projectile;
 
+/// Sent every time a ship fires its gun.
+#[derive(Event)]
+pub struct ShotFired {
+    pub position: Vec3,
+}
+
+/// Sent when a ship is destroyed.
+#[derive(Event)]
+pub struct ShipDestroyed {
+    pub position: Vec3,
+    /// Index of the player flying it, see [`Player::index`].
+    pub player: usize,
+}
+
+/// Send this to bring a player back into the match with a new ship,
+/// warping in at a random spot away from the other ships.
+#[derive(Event)]
+pub struct RespawnShip {
+    /// See [`Player::index`].
+    pub player: usize,
+}
+
+/// The shrinking ring a respawned ship warps in from.
+#[derive(Component)]
+struct WarpIn(Timer);
+
 /// This plugin handles player related stuff like movement
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            OnEnter(GameState::Playing),
-            (spawn_player_1, spawn_player_2),
-        )
-        .add_systems(
-            Update,
-            (move_player, shoot).run_if(in_state(GameState::Playing)),
-        );
+        app.init_resource::<PlayerCount>()
+            .init_resource::<FlightAssists>()
+            .add_event::<ShotFired>()
+            .add_event::<ShipDestroyed>()
+            .add_event::<RespawnShip>()
+            .add_systems(SpawnMatch, spawn_players)
+            .add_systems(
+                Update,
+                (
+                    move_player.before(apply_forces),
+                    tumble_ships.after(bounce_bodies),
+                    shoot,
+                    // Either can destroy a ship, the first one to do it sends `ShipDestroyed`
+                    damage_ships
+                        .after(bounce_bodies)
+                        .before(detect_destroyed_ships),
+                    detect_destroyed_ships.after(check_for_collisions),
+                    wear_off_invulnerability,
+                    respawn_ships,
+                    animate_warp_ins,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
-fn spawn_player_1(mut commands: Commands, textures: Res<TextureAssets>) {
-    commands.spawn(create_player(&textures, Vec3::new(-600., 0., 0.), 1));
+/// Spawns a ship for every player at the spawn points of the selected level,
+/// or at random ones if the [`MatchConfig`] says so.
+#[allow(clippy::too_many_arguments)]
+fn spawn_players(
+    mut commands: Commands,
+    player_count: Res<PlayerCount>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    selected_ships: Res<SelectedShips>,
+    ship_classes: Res<Assets<ShipClass>>,
+    team_mode: Res<TeamMode>,
+    flight_assists: Res<FlightAssists>,
+    config: Res<MatchConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    let level = levels
+        .get(&selected_level.0)
+        .expect("Levels are loaded before leaving `GameState::Loading`");
+    let spawn_points = match config.spawns {
+        SpawnPlacement::Level => level.spawn_points(player_count.0),
+        SpawnPlacement::Random => {
+            level.random_spawn_points(player_count.0, rng.stream(RngStream::Spawns))
+        }
+    };
+    for (index, (spawn_point, ship)) in spawn_points.iter().zip(&selected_ships.0).enumerate() {
+        let ship_class = ship_classes
+            .get(ship)
+            .expect("Ship classes are loaded before leaving `GameState::Loading`");
+        let color = team_mode.side_color(team_mode.side(index));
+        let mut ship = commands.spawn(create_player(
+            ship_class,
+            spawn_point,
+            level.spawn_velocity(spawn_point, ship_class.mass, config.gravity),
+            index as u8 + 1,
+            color,
+            config.weapon_set,
+        ));
+        set_up_player(&mut ship, index, &team_mode, &flight_assists);
+    }
 }
 
-fn spawn_player_2(mut commands: Commands, textures: Res<TextureAssets>) {
-    commands.spawn(create_player(&textures, Vec3::new(600., 0., 0.), 2));
+/// Brings players back with a fresh ship of their class, invulnerable for a bit like
+/// at the start of a match. A ring collapses onto the spot they warp in at.
+#[allow(clippy::too_many_arguments)]
+fn respawn_ships(
+    mut commands: Commands,
+    mut respawns: EventReader<RespawnShip>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ships: Query<&Transform, With<Player>>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    selected_ships: Res<SelectedShips>,
+    ship_classes: Res<Assets<ShipClass>>,
+    team_mode: Res<TeamMode>,
+    flight_assists: Res<FlightAssists>,
+    config: Res<MatchConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    if respawns.is_empty() {
+        return;
+    }
+    let level = levels
+        .get(&selected_level.0)
+        .expect("Levels are loaded before leaving `GameState::Loading`");
+    let mut opponents: Vec<Vec2> = ships
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    for respawn in respawns.read() {
+        let index = respawn.player;
+        let ship_class = ship_classes
+            .get(&selected_ships.0[index])
+            .expect("Ship classes are loaded before leaving `GameState::Loading`");
+        let spawn_point = level.random_spawn_point(&opponents, rng.stream(RngStream::Spawns));
+        opponents.push(spawn_point.position);
+        let color = team_mode.side_color(team_mode.side(index));
+        let mut ship = commands.spawn(create_player(
+            ship_class,
+            &spawn_point,
+            level.spawn_velocity(&spawn_point, ship_class.mass, config.gravity),
+            index as u8 + 1,
+            color,
+            config.weapon_set,
+        ));
+        set_up_player(&mut ship, index, &team_mode, &flight_assists);
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(shape::Circle::new(ship_class.sprite_size.max_element()).into())
+                    .into(),
+                material: materials.add(color.with_a(WARP_IN_ALPHA).into()),
+                // Behind the ship
+                transform: Transform::from_translation(spawn_point.position.extend(-0.5))
+                    .with_scale(Vec3::splat(WARP_IN_SCALE)),
+                ..default()
+            },
+            WarpIn(Timer::from_seconds(WARP_IN_SECONDS, TimerMode::Once)),
+            MatchEntity,
+        ));
+    }
+}
+
+fn animate_warp_ins(
+    mut commands: Commands,
+    mut warp_ins: Query<(Entity, &mut WarpIn, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut warp_in, mut transform) in &mut warp_ins {
+        if warp_in.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.scale = Vec3::splat(WARP_IN_SCALE * warp_in.0.percent_left());
+    }
+}
+
+/// Gives the ship of the player at `index` what the match setup and ship selection picked for it.
+fn set_up_player(
+    ship: &mut EntityCommands,
+    index: usize,
+    team_mode: &TeamMode,
+    flight_assists: &FlightAssists,
+) {
+    if let Some(team) = team_mode.team(index) {
+        ship.insert(team);
+    }
+    if flight_assists.0[index] {
+        ship.insert(FlightAssist);
+    }
 }
 
 fn create_player(
-    textures: &Res<TextureAssets>,
-    position: Vec3,
+    ship_class: &ShipClass,
+    spawn_point: &SpawnPoint,
+    velocity: Vec2,
     player_number: u8,
-) -> (SpriteBundle, Player, Gun, PhysicsBundle, Collider) {
+    color: Color,
+    weapon_set: WeaponSet,
+) -> (
+    SpriteBundle,
+    Player,
+    Engine,
+    Guns,
+    Hull,
+    PhysicsBundle,
+    Collider,
+    Invulnerable,
+    MatchEntity,
+) {
     (
         SpriteBundle {
-            texture: textures.bevy.clone(),
-            transform: Transform::from_translation(position).with_scale(Vec3::new(0.2, 0.2, 0.2)),
+            texture: ship_class.texture.clone(),
+            sprite: Sprite {
+                color,
+                custom_size: Some(ship_class.sprite_size),
+                ..default()
+            },
+            transform: Transform::from_translation(spawn_point.position.extend(0.))
+                .with_rotation(Quat::from_rotation_z(spawn_point.rotation)),
             ..Default::default()
         },
         Player {
             number: player_number,
         },
-        Gun {
-            cooldown_timer: Timer::from_seconds(0.5, TimerMode::Once),
+        Engine {
+            thrust: ship_class.thrust,
+            reverse_thrust: ship_class.reverse_thrust(),
+            strafe_thrust: ship_class.strafe_thrust,
+            turn_rate: ship_class.turn_rate,
+            spin: 0.,
+            fuel: ship_class.fuel.map(|tank| Fuel {
+                tank,
+                remaining: tank.capacity,
+            }),
+            thrusting: false,
+        },
+        Guns(
+            ship_class
+                .weapons
+                .iter()
+                .map(|weapon| weapon_set.apply(weapon))
+                .map(|weapon| Gun {
+                    cooldown_timer: Timer::from_seconds(weapon.cooldown, TimerMode::Once),
+                    weapon,
+                })
+                .collect(),
+        ),
+        Hull(FULL_HULL),
+        PhysicsBundle {
+            mass: Mass(ship_class.mass),
+            velocity: Velocity(velocity.extend(0.)),
+            ..default()
         },
-        PhysicsBundle::default(),
         Collider {
-            dimensions: Vec2::new(51.2, 51.2),
+            dimensions: ship_class.collider,
             destroyable: true,
+            restitution: ship_class.restitution,
         },
+        Invulnerable(Timer::from_seconds(SPAWN_INVULNERABILITY, TimerMode::Once)),
+        MatchEntity,
     )
 }
 
-/// Rotates the player and thrusts.
-/// The thrust creates a force, which is then used to calculate the
+/// Rotates the player and fires its thrusters.
+/// Every thruster creates its own force, which is then used to calculate the
 /// net force in the `PhysicsPlugin`.
-fn move_player(
+/// Ships with a fuel tank can only thrust while there's fuel left,
+/// the side and retro thrusters burning it in proportion to their force.
+pub fn move_player(
     time: Res<Time>,
     actions: Res<Actions>,
-    mut player_query: Query<(&mut Transform, &mut Forces, &Player)>,
+    config: Res<MatchConfig>,
+    mut player_query: Query<(
+        &mut Transform,
+        &mut Forces,
+        &mut Engine,
+        &Velocity,
+        &Mass,
+        &Collider,
+        &Player,
+        Has<FlightAssist>,
+    )>,
+    stars_query: Query<(&Transform, &Mass, Option<&Velocity>), (With<Star>, Without<Player>)>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (mut transform, mut forces, mut engine, velocity, mass, collider, player, assisted) in
+        &mut player_query
+    {
+        let player_actions = &actions.player_actions[player.index()];
+        let player_forward = transform.up(); // Seems confusing but "forward" is "up" in the 2D world
+        let player_right = transform.right();
+
+        let inertia = moment_of_inertia(mass.0, collider.dimensions);
+        // Side thrusters at the nose and tail turn the ship
+        let lever = collider.dimensions.max_element() / 2.;
+        let rotation_damping = match player_actions.rotation {
+            Some(rotation) => {
+                // Steering turns the ship directly, taking over from any tumble
+                engine.spin = 0.;
+                transform.rotate_z(rotation * engine.turn_rate * delta_seconds);
+                0.
+            }
+            None if assisted && delta_seconds > 0. => {
+                let max_torque = engine.thrust * ASSIST_THRUST * lever;
+                (-engine.spin * inertia / delta_seconds).clamp(-max_torque, max_torque)
+            }
+            None => 0.,
+        };
+
+        // Newtons
+        let thrust = if player_actions.thrust {
+            player_forward * engine.thrust
+        } else {
+            Vec3::ZERO
+        };
+        let reverse_thrust = if player_actions.reverse {
+            -player_forward * engine.reverse_thrust
+        } else {
+            Vec3::ZERO
+        };
+        let strafe = player_right * player_actions.strafe * engine.strafe_thrust.unwrap_or(0.);
+        let manual = player_actions.thrust || player_actions.reverse || strafe != Vec3::ZERO;
+        let assist = if assisted && !manual {
+            let target = orbit_velocity(
+                transform.translation,
+                velocity.0,
+                mass.0,
+                config.gravity,
+                &stars_query,
+            );
+            assist_force(target - velocity.0, mass.0, engine.thrust * ASSIST_THRUST)
+        } else {
+            Vec3::ZERO
+        };
+
+        let throttle = ([thrust, reverse_thrust, strafe, assist]
+            .iter()
+            .map(|force| force.length())
+            .sum::<f32>()
+            + rotation_damping.abs() / lever)
+            / engine.thrust;
+        engine.thrusting = engine.burn(throttle, delta_seconds);
+        let scale = if engine.thrusting { 1. } else { 0. };
+        forces.set(ForceSource::Thrust, thrust * scale);
+        forces.set(ForceSource::ReverseThrust, reverse_thrust * scale);
+        forces.set(ForceSource::Strafe, strafe * scale);
+        forces.set(ForceSource::FlightAssist, assist * scale);
+        forces.set(
+            ForceSource::RotationDamping,
+            Vec3::Z * rotation_damping * scale,
+        );
+
+        engine.spin += forces.torque() / inertia * delta_seconds;
+        transform.rotate_z(engine.spin * delta_seconds);
+    }
+}
+
+/// Moment of inertia of a ship, as if it were a solid rectangle of the size of its collider.
+fn moment_of_inertia(mass: f32, dimensions: Vec2) -> f32 {
+    mass * dimensions.length_squared() / 12.
+}
+
+/// Knocks ships into a tumble when they bounce off something off center.
+/// They never tumble faster than they can turn.
+fn tumble_ships(
+    mut impacts: EventReader<ImpactEvent>,
+    mut ships: Query<(&Transform, &Collider, &Mass, &mut Engine)>,
 ) {
-    let rotation_speed = 2.0;
-    for (mut transform, mut forces, player) in &mut player_query {
-        let player_actions = &actions.player_actions[(player.number - 1) as usize];
-        let thrust_force = if player_actions.thrust { 200. } else { 0. }; // Newtons
-        let player_forward = transform.up().clone(); // Seems confusing but "forward" is "up" in the 2D world
-        if let Some(rotation) = player_actions.rotation {
-            transform.rotate_z(rotation * rotation_speed * time.delta_seconds());
+    for impact in impacts.read() {
+        let (entity1, entity2) = impact.entities;
+        for (entity, impulse) in [(entity1, -impact.impulse), (entity2, impact.impulse)] {
+            let Ok((transform, collider, mass, mut engine)) = ships.get_mut(entity) else {
+                continue;
+            };
+            // Where the ship got hit, on its edge
+            let half_size = collider.dimensions / 2.;
+            let contact = (impact.position - transform.translation)
+                .truncate()
+                .clamp(-half_size, half_size);
+            let spin = contact.perp_dot(impulse.truncate())
+                / moment_of_inertia(mass.0, collider.dimensions);
+            engine.spin = (engine.spin + spin).clamp(-engine.turn_rate, engine.turn_rate);
         }
-        forces
-            .0
-            .insert("thrust".to_string(), player_forward * thrust_force);
     }
 }
 
+/// The velocity flight assist steers towards: a circular orbit around the star pulling
+/// the hardest, going the same way around as the ship. Without stars, it holds still.
+fn orbit_velocity(
+    position: Vec3,
+    velocity: Vec3,
+    mass: f32,
+    gravity: f32,
+    stars_query: &Query<(&Transform, &Mass, Option<&Velocity>), (With<Star>, Without<Player>)>,
+) -> Vec3 {
+    let strongest = stars_query
+        .iter()
+        .filter(|(transform, star_mass, _)| star_mass.0 > 0. && transform.translation != position)
+        .max_by(|(first, first_mass, _), (second, second_mass, _)| {
+            let pull = |transform: &Transform, star_mass: &Mass| {
+                gravitational_force(star_mass.0, mass, transform.translation - position).length()
+            };
+            pull(first, first_mass).total_cmp(&pull(second, second_mass))
+        });
+    let Some((star_transform, star_mass, star_velocity)) = strongest else {
+        return Vec3::ZERO;
+    };
+    let star_velocity = star_velocity.map_or(Vec3::ZERO, |velocity| velocity.0);
+    let offset = (position - star_transform.translation).truncate();
+    let relative_velocity = (velocity - star_velocity).truncate();
+    let distance = offset.length();
+    let direction = if offset.perp_dot(relative_velocity) >= 0. {
+        offset.perp()
+    } else {
+        -offset.perp()
+    } / distance;
+    let speed = circular_orbit_speed(star_mass.0 * gravity, mass, distance);
+    star_velocity + (direction * speed).extend(0.)
+}
+
+/// Force flight assist uses to close the gap `correction` with its target velocity.
+fn assist_force(correction: Vec3, mass: f32, max_force: f32) -> Vec3 {
+    if correction.length() < ASSIST_TOLERANCE {
+        return Vec3::ZERO;
+    }
+    (correction * mass * ASSIST_RESPONSE).clamp_length_max(max_force)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn shoot(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut query: Query<(&Transform, &mut Gun, &Player)>,
+    mut query: Query<(
+        &Transform,
+        &mut Velocity,
+        &Mass,
+        &mut Guns,
+        &Player,
+        Option<&Team>,
+        Has<RapidFire>,
+    )>,
+    mut shots: EventWriter<ShotFired>,
     actions: Res<Actions>,
+    settings: Res<Settings>,
+    team_mode: Res<TeamMode>,
     time: Res<Time>,
 ) {
-    for (transform, mut gun, player) in query.iter_mut() {
-        if gun.cooldown_timer.tick(time.delta()).finished() {
-            let player_actions = &actions.player_actions[(player.number - 1) as usize];
-            if player_actions.fire {
-                commands.spawn(create_projectile(
-                    transform.translation.clone() + transform.up() * 50.,
-                    &transform.up(),
+    for (transform, mut velocity, mass, mut guns, player, team, rapid_fire) in query.iter_mut() {
+        let player_actions = &actions.player_actions[player.index()];
+        let reload = if rapid_fire {
+            time.delta() * 2
+        } else {
+            time.delta()
+        };
+        let mut fired = false;
+        for gun in guns.0.iter_mut() {
+            if gun.cooldown_timer.tick(reload).finished() && player_actions.fire {
+                // Projectiles have the color and team of the ship that fired them,
+                // the color of its side rather than of its sprite, which blinks after spawning
+                let mut projectile = commands.spawn(create_projectile(
+                    &gun.weapon,
+                    transform,
+                    velocity.0,
+                    team_mode.side_color(team_mode.side(player.index())),
                     &mut meshes,
                     &mut materials,
                 ));
+                if let Some(team) = team {
+                    projectile.insert(*team);
+                }
+                if settings.gameplay.recoil {
+                    // The ship gets the opposite of the momentum given to the projectile
+                    let muzzle_velocity = transform.up() * gun.weapon.muzzle_speed;
+                    velocity.0 -= muzzle_velocity * gun.weapon.projectile_mass / mass.0;
+                }
                 gun.cooldown_timer.reset();
+                fired = true;
             }
         }
+        // Guns firing together make a single sound
+        if fired {
+            shots.send(ShotFired {
+                position: transform.translation,
+            });
+        }
     }
 }
 
+/// Takes hull off ships in proportion to how hard they bounced off something,
+/// destroying them once it's all gone.
+fn damage_ships(
+    mut commands: Commands,
+    mut impacts: EventReader<ImpactEvent>,
+    mut destroyed_ships: EventWriter<ShipDestroyed>,
+    mut ships: Query<(&Transform, &Player, &mut Hull), Without<Invulnerable>>,
+    settings: Res<Settings>,
+) {
+    if !settings.gameplay.collision_damage {
+        impacts.clear();
+        return;
+    }
+    for impact in impacts.read() {
+        let (entity1, entity2) = impact.entities;
+        for entity in [entity1, entity2] {
+            let Ok((transform, player, mut hull)) = ships.get_mut(entity) else {
+                continue;
+            };
+            // Already destroyed by an earlier impact this frame
+            if hull.0 <= 0. {
+                continue;
+            }
+            hull.0 -= impact.energy * DAMAGE_PER_ENERGY;
+            if hull.0 <= 0. {
+                commands.entity(entity).despawn();
+                destroyed_ships.send(ShipDestroyed {
+                    position: transform.translation,
+                    player: player.index(),
+                });
+            }
+        }
+    }
+}
+
+/// Makes invulnerable ships blink until it wears off.
+fn wear_off_invulnerability(
+    mut commands: Commands,
+    mut ships: Query<(Entity, &mut Invulnerable, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (ship, mut invulnerable, mut sprite) in &mut ships {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(ship).remove::<Invulnerable>();
+            sprite.color.set_a(1.);
+            continue;
+        }
+        let visible = (invulnerable.0.elapsed_secs() * INVULNERABLE_BLINK_RATE).fract() < 0.5;
+        sprite
+            .color
+            .set_a(if visible { 1. } else { INVULNERABLE_ALPHA });
+    }
+}
+
+/// Ships crash into anything they can't bounce off, and any crash involving one destroys it.
+/// Their hull is emptied, so ships already destroyed this frame aren't destroyed again.
+fn detect_destroyed_ships(
+    mut collisions: EventReader<CollisionEvent>,
+    mut destroyed_ships: EventWriter<ShipDestroyed>,
+    mut ships: Query<(&Transform, &Player, &mut Hull)>,
+) {
+    for collision in collisions.read() {
+        let (entity1, entity2) = collision.entities;
+        for entity in [entity1, entity2] {
+            let Ok((transform, player, mut hull)) = ships.get_mut(entity) else {
+                continue;
+            };
+            // Touching several things in the same frame, or out of hull already
+            if hull.0 <= 0. {
+                continue;
+            }
+            hull.0 = 0.;
+            destroyed_ships.send(ShipDestroyed {
+                position: transform.translation,
+                player: player.index(),
+            });
+        }
+    }
+}
+
+/// Where a projectile fired by `weapon` starts, and how fast it goes.
+/// Projectiles leave the muzzle on top of the velocity of the ship firing them.
+pub fn launch(weapon: &Weapon, ship_transform: &Transform, ship_velocity: Vec3) -> (Vec3, Vec3) {
+    (
+        ship_transform.transform_point(weapon.muzzle_offset.extend(0.)),
+        ship_velocity + ship_transform.up() * weapon.muzzle_speed,
+    )
+}
+
 fn create_projectile(
-    position: Vec3,
-    up: &Vec3,
+    weapon: &Weapon,
+    ship_transform: &Transform,
+    ship_velocity: Vec3,
+    color: Color,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
 ) -> (
@@ -124,24 +769,29 @@ fn create_projectile(
     Projectile,
     PhysicsBundle,
     Collider,
+    MatchEntity,
 ) {
-    let initial_velocity = *up * 500.;
+    let (position, initial_velocity) = launch(weapon, ship_transform, ship_velocity);
     (
         MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::new(5.).into()).into(),
-            material: materials.add(Color::RED.into()),
+            mesh: meshes
+                .add(shape::Circle::new(weapon.projectile_radius).into())
+                .into(),
+            material: materials.add(color.into()),
             transform: Transform::from_translation(position),
             ..default()
         },
         Projectile,
         PhysicsBundle {
-            mass: Mass(0.1), // 100 grams
+            mass: Mass(weapon.projectile_mass),
             velocity: Velocity(initial_velocity),
             ..default()
         },
         Collider {
-            dimensions: Vec2::new(5., 5.),
+            dimensions: Vec2::splat(weapon.projectile_radius),
             destroyable: true,
+            restitution: None,
         },
+        MatchEntity,
     )
 }
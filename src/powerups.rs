@@ -0,0 +1,223 @@
+use crate::level::{Level, SelectedLevel};
+use crate::match_setup::MatchConfig;
+use crate::physics::{Collider, Mass, PhysicsBundle, Velocity};
+use crate::player::{Engine, Hull, Player, RapidFire};
+use crate::rng::{GameRng, RngStream};
+use crate::{GameState, MatchEntity, SpawnMatch};
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use rand::Rng;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+pub struct PowerUpsPlugin;
+
+/// This plugin drops power-ups in orbit during matches, as often as the [`MatchConfig`] says.
+/// Ships pick them up by flying through them, and they fade away if nobody does.
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerUpSpawner>()
+            .add_systems(SpawnMatch, reset_spawner)
+            .add_systems(
+                Update,
+                (
+                    spawn_power_ups,
+                    expire_power_ups,
+                    pick_up_power_ups,
+                    end_rapid_fire,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// How often power-ups show up.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PowerUpFrequency {
+    #[default]
+    Off,
+    Rare,
+    Normal,
+    Frequent,
+}
+
+impl PowerUpFrequency {
+    pub fn cycle(&mut self) {
+        *self = match self {
+            PowerUpFrequency::Off => PowerUpFrequency::Rare,
+            PowerUpFrequency::Rare => PowerUpFrequency::Normal,
+            PowerUpFrequency::Normal => PowerUpFrequency::Frequent,
+            PowerUpFrequency::Frequent => PowerUpFrequency::Off,
+        };
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerUpFrequency::Off => "Off",
+            PowerUpFrequency::Rare => "Rare",
+            PowerUpFrequency::Normal => "Normal",
+            PowerUpFrequency::Frequent => "Frequent",
+        }
+    }
+
+    /// Seconds between two power-ups.
+    fn interval(&self) -> Option<f32> {
+        match self {
+            PowerUpFrequency::Off => None,
+            PowerUpFrequency::Rare => Some(20.),
+            PowerUpFrequency::Normal => Some(12.),
+            PowerUpFrequency::Frequent => Some(6.),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+enum PowerUp {
+    /// Restores the hull.
+    Repair,
+    /// Fills up the fuel tank.
+    Refuel,
+    /// Guns reload twice as fast for a while.
+    RapidFire,
+}
+
+/// Every drop is one of these, picked at random.
+const POWER_UPS: [PowerUp; 3] = [PowerUp::Repair, PowerUp::Refuel, PowerUp::RapidFire];
+/// No more power-ups are dropped while this many are waiting to be picked up.
+const MAX_POWER_UPS: usize = 3;
+const POWER_UP_RADIUS: f32 = 12.;
+const POWER_UP_MASS: f32 = 0.5;
+/// Seconds a power-up stays around before fading away.
+const POWER_UP_LIFETIME: f32 = 15.;
+const RAPID_FIRE_SECONDS: f32 = 8.;
+/// Power-ups are dropped on orbits between these distances,
+/// relative to the widest orbit that fits the arena.
+const POWER_UP_ORBITS: (f32, f32) = (0.4, 0.85);
+
+impl PowerUp {
+    fn color(&self) -> Color {
+        match self {
+            PowerUp::Repair => Color::rgb(0.3, 1., 0.4),
+            PowerUp::Refuel => Color::rgb(1., 0.7, 0.2),
+            PowerUp::RapidFire => Color::rgb(1., 0.3, 0.8),
+        }
+    }
+}
+
+/// Counts down to the next power-up of the current match.
+#[derive(Resource, Default)]
+struct PowerUpSpawner(Option<Timer>);
+
+/// Time left before a power-up fades away.
+#[derive(Component)]
+struct Lifetime(Timer);
+
+fn reset_spawner(mut spawner: ResMut<PowerUpSpawner>, config: Res<MatchConfig>) {
+    spawner.0 = config
+        .power_ups
+        .interval()
+        .map(|interval| Timer::from_seconds(interval, TimerMode::Repeating));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_power_ups(
+    mut commands: Commands,
+    mut spawner: ResMut<PowerUpSpawner>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    power_ups: Query<(), With<PowerUp>>,
+    selected_level: Res<SelectedLevel>,
+    levels: Res<Assets<Level>>,
+    config: Res<MatchConfig>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Some(timer) = spawner.0.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.delta()).just_finished() || power_ups.iter().len() >= MAX_POWER_UPS {
+        return;
+    }
+    let level = levels
+        .get(&selected_level.0)
+        .expect("Levels are loaded before leaving `GameState::Loading`");
+    let rng = rng.stream(RngStream::PowerUps);
+    let power_up = POWER_UPS[rng.gen_range(0..POWER_UPS.len())];
+    let angle = rng.gen_range(0. ..TAU);
+    let (closest, furthest) = POWER_UP_ORBITS;
+    let distance = level.max_orbit_distance() * rng.gen_range(closest..furthest);
+    let spawn_point = level.orbit(angle, distance, POWER_UP_MASS, config.gravity);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(shape::Circle::new(POWER_UP_RADIUS).into())
+                .into(),
+            material: materials.add(power_up.color().into()),
+            transform: Transform::from_translation(spawn_point.position.extend(0.)),
+            ..default()
+        },
+        power_up,
+        Lifetime(Timer::from_seconds(POWER_UP_LIFETIME, TimerMode::Once)),
+        PhysicsBundle {
+            mass: Mass(POWER_UP_MASS),
+            velocity: Velocity(spawn_point.velocity.unwrap_or_default().extend(0.)),
+            ..default()
+        },
+        MatchEntity,
+    ));
+}
+
+fn expire_power_ups(
+    mut commands: Commands,
+    mut power_ups: Query<(Entity, &mut Lifetime)>,
+    time: Res<Time>,
+) {
+    for (entity, mut lifetime) in &mut power_ups {
+        if lifetime.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Gives power-ups to the ships flying through them.
+/// They don't have a collider, so nothing crashes into them.
+fn pick_up_power_ups(
+    mut commands: Commands,
+    power_ups: Query<(Entity, &Transform, &PowerUp)>,
+    mut ships: Query<(Entity, &Transform, &Collider, &mut Hull, &mut Engine), With<Player>>,
+) {
+    for (power_up_entity, power_up_transform, power_up) in &power_ups {
+        let picked_up_by = ships.iter_mut().find(|(_, transform, collider, _, _)| {
+            transform
+                .translation
+                .distance(power_up_transform.translation)
+                < collider.dimensions.max_element() / 2. + POWER_UP_RADIUS
+        });
+        let Some((ship, _, _, mut hull, mut engine)) = picked_up_by else {
+            continue;
+        };
+        match power_up {
+            PowerUp::Repair => hull.repair(),
+            PowerUp::Refuel => engine.refuel(),
+            PowerUp::RapidFire => {
+                commands.entity(ship).insert(RapidFire(Timer::from_seconds(
+                    RAPID_FIRE_SECONDS,
+                    TimerMode::Once,
+                )));
+            }
+        }
+        commands.entity(power_up_entity).despawn();
+    }
+}
+
+fn end_rapid_fire(
+    mut commands: Commands,
+    mut ships: Query<(Entity, &mut RapidFire)>,
+    time: Res<Time>,
+) {
+    for (ship, mut rapid_fire) in &mut ships {
+        if rapid_fire.0.tick(time.delta()).finished() {
+            commands.entity(ship).remove::<RapidFire>();
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::menu::ButtonColors;
+use crate::modes::MatchResult;
+use crate::rng::GameRng;
+use crate::{despawn_match_entities, GameState, MatchEntity};
+use bevy::prelude::*;
+
+pub struct ResultsPlugin;
+
+/// This plugin shows how the last match went, as told by its game mode,
+/// and the seed to play it again with.
+/// The finished match stays frozen behind the results until the players leave it.
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Results), setup_results)
+            .add_systems(
+                Update,
+                click_results_button.run_if(in_state(GameState::Results)),
+            )
+            .add_systems(OnExit(GameState::Results), cleanup_results);
+    }
+}
+
+#[derive(Component)]
+struct ResultsScreen;
+
+#[derive(Component, Clone, Copy)]
+enum ResultsButton {
+    PlayAgain,
+    Menu,
+}
+
+impl ResultsButton {
+    fn label(&self) -> &'static str {
+        match self {
+            ResultsButton::PlayAgain => "Play again",
+            ResultsButton::Menu => "Back to menu",
+        }
+    }
+}
+
+fn setup_results(mut commands: Commands, match_result: Res<MatchResult>, rng: Res<GameRng>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                ..default()
+            },
+            ResultsScreen,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                match_result.headline.clone(),
+                TextStyle {
+                    font_size: 60.0,
+                    color: match_result.color,
+                    ..default()
+                },
+            ));
+            for (line, color) in &match_result.details {
+                children.spawn(TextBundle::from_section(
+                    line.clone(),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: *color,
+                        ..default()
+                    },
+                ));
+            }
+            children.spawn(TextBundle::from_section(
+                format!("Seed: {}", rng.seed()),
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::rgb(0.6, 0.6, 0.6),
+                    ..default()
+                },
+            ));
+            for button in [ResultsButton::PlayAgain, ResultsButton::Menu] {
+                let button_colors = ButtonColors::default();
+                children
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(260.0),
+                                height: Val::Px(50.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..Default::default()
+                        },
+                        button_colors,
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle {
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn click_results_button(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    match_entities: Query<Entity, With<MatchEntity>>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            &ResultsButton,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                despawn_match_entities(&mut commands, &match_entities);
+                match button {
+                    // With the old match gone, entering `Playing` starts a fresh one
+                    ResultsButton::PlayAgain => next_state.set(GameState::Playing),
+                    ResultsButton::Menu => next_state.set(GameState::Menu),
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+fn cleanup_results(mut commands: Commands, results: Query<Entity, With<ResultsScreen>>) {
+    for entity in results.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,72 @@
+use crate::match_setup::MatchConfig;
+use crate::{spawn_match, GameState, MatchEntity};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+pub struct RngPlugin;
+
+/// This plugin seeds the [`GameRng`] at the start of every match,
+/// with the seed of the [`MatchConfig`] if it has one.
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>().add_systems(
+            OnEnter(GameState::Playing),
+            // Only for new matches, later rounds keep drawing from the same streams
+            seed_match
+                .before(spawn_match)
+                .run_if(not(any_with_component::<MatchEntity>())),
+        );
+    }
+}
+
+/// Subsystems drawing random numbers, each from its own stream.
+/// Adding draws to one of them doesn't change what the others get.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngStream {
+    Spawns,
+    PowerUps,
+}
+
+impl RngStream {
+    const ALL: [RngStream; 2] = [RngStream::Spawns, RngStream::PowerUps];
+}
+
+/// All the randomness of a match comes from here, so replaying its seed plays it out the same way.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    streams: [StdRng; RngStream::ALL.len()],
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: RngStream::ALL.map(|stream| {
+                // Spread the seeds of the streams apart, `seed_from_u64` scrambles them further
+                StdRng::seed_from_u64(
+                    seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                )
+            }),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        &mut self.streams[stream as usize]
+    }
+}
+
+fn seed_match(mut rng: ResMut<GameRng>, config: Res<MatchConfig>) {
+    *rng = GameRng::new(config.seed.unwrap_or_else(rand::random));
+}
@@ -0,0 +1,629 @@
+use crate::menu::ButtonColors;
+use crate::{GameState, LaunchOptions, MatchEntity};
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::{PresentMode, PrimaryWindow, WindowFocused, WindowMode};
+use serde::{Deserialize, Serialize};
+
+/// On the web the canvas is sized by the page and mobile apps are always fullscreen,
+/// so only desktop builds get to configure the window.
+const WINDOW_CONFIGURABLE: bool = !cfg!(any(
+    target_arch = "wasm32",
+    target_os = "android",
+    target_os = "ios"
+));
+
+/// Resolutions offered in the settings menu (desktop only).
+const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+
+pub struct SettingsPlugin;
+
+/// This plugin owns the user's [`Settings`]: it draws the settings menu during
+/// `GameState::Settings`, applies changes as soon as they happen and persists them
+/// when the menu is left.
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // The native binary loads the settings itself to configure the window before startup
+        if !app.world.contains_resource::<Settings>() {
+            app.insert_resource(Settings::load());
+        }
+        app.add_systems(OnEnter(GameState::Settings), setup_settings_menu)
+            .add_systems(
+                Update,
+                (
+                    click_settings_button,
+                    drag_volume_slider,
+                    update_settings_menu.run_if(resource_changed::<Settings>()),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_video_settings.run_if(resource_changed::<Settings>()),
+                    pause_on_focus_loss.run_if(in_state(GameState::Playing)),
+                ),
+            )
+            .add_systems(
+                OnExit(GameState::Settings),
+                (save_settings, cleanup_settings_menu),
+            );
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: AudioSettings,
+    pub video: VideoSettings,
+    pub gameplay: GameplaySettings,
+}
+
+/// All volumes go from 0 to 1.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            music_volume: 0.7,
+            sfx_volume: 1.,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Volume for music, taking the master volume into account.
+    pub fn music(&self) -> f64 {
+        (self.master_volume * self.music_volume) as f64
+    }
+
+    /// Volume for sound effects, taking the master volume into account.
+    pub fn sfx(&self) -> f64 {
+        (self.master_volume * self.sfx_volume) as f64
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub fullscreen: bool,
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            resolution: RESOLUTIONS[0],
+            vsync: true,
+        }
+    }
+}
+
+impl VideoSettings {
+    /// These settings, with the window options the game was started with instead.
+    pub fn launched_with(&self, options: &LaunchOptions) -> Self {
+        Self {
+            fullscreen: options.fullscreen.unwrap_or(self.fullscreen),
+            resolution: options.window_size.unwrap_or(self.resolution),
+            ..self.clone()
+        }
+    }
+
+    /// Configures the window according to these settings.
+    /// Only vsync applies on platforms where the window can't be configured.
+    pub fn apply(&self, window: &mut Window) {
+        window.present_mode = if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+        if !WINDOW_CONFIGURABLE {
+            return;
+        }
+        window.mode = if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        let (width, height) = self.resolution;
+        if window.resolution.width() != width as f32 || window.resolution.height() != height as f32
+        {
+            window.resolution.set(width as f32, height as f32);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GameplaySettings {
+    /// Pause the match when the window loses focus.
+    pub pause_on_focus_loss: bool,
+    /// In team matches, projectiles hit ships of the team that fired them.
+    pub friendly_fire: bool,
+    /// Shake the camera when ships explode.
+    pub screen_shake: bool,
+    /// Zoom in on the ships instead of always showing the whole arena.
+    pub camera_zoom: bool,
+    /// Draw where every ship and its shots are headed.
+    pub trajectories: bool,
+    /// Firing pushes ships back.
+    pub recoil: bool,
+    /// Ships bouncing off something lose hull in proportion to how hard they hit it.
+    pub collision_damage: bool,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            pause_on_focus_loss: true,
+            friendly_fire: false,
+            screen_shake: true,
+            camera_zoom: false,
+            trajectories: false,
+            recoil: true,
+            collision_damage: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the stored settings, falling back to the defaults if there are none
+    /// or they can't be read.
+    pub fn load() -> Self {
+        let Some(stored) = storage::read() else {
+            return Self::default();
+        };
+        ron::from_str(&stored).unwrap_or_else(|error| {
+            warn!("Ignoring invalid settings: {error}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let serialized = match ron::ser::to_string_pretty(self, default()) {
+            Ok(serialized) => serialized,
+            Err(error) => {
+                warn!("Failed to serialize settings: {error}");
+                return;
+            }
+        };
+        if let Err(error) = storage::write(&serialized) {
+            warn!("Failed to save settings: {error}");
+        }
+    }
+}
+
+/// Settings are stored in the user's config directory.
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("star_fighters").join("settings.ron"))
+    }
+
+    pub fn read() -> Option<String> {
+        std::fs::read_to_string(path()?).ok()
+    }
+
+    pub fn write(contents: &str) -> Result<(), String> {
+        let path = path().ok_or("no config directory on this platform")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+        }
+        std::fs::write(path, contents).map_err(|error| error.to_string())
+    }
+}
+
+/// On the web, settings are stored in the browser's `localStorage`.
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    const KEY: &str = "star_fighters.settings";
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read() -> Option<String> {
+        local_storage()?.get_item(KEY).ok()?
+    }
+
+    pub fn write(contents: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or("localStorage is not available")?
+            .set_item(KEY, contents)
+            .map_err(|error| format!("{error:?}"))
+    }
+}
+
+/// Only touches the window when the video settings themselves changed,
+/// so changing the volume doesn't undo the user resizing the window.
+/// The window options the game was started with hold until then.
+fn apply_video_settings(
+    settings: Res<Settings>,
+    launch_options: Res<LaunchOptions>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    mut applied: Local<Option<VideoSettings>>,
+) {
+    if applied.as_ref() == Some(&settings.video) {
+        return;
+    }
+    if let Ok(mut window) = window.get_single_mut() {
+        match *applied {
+            None => settings
+                .video
+                .launched_with(&launch_options)
+                .apply(&mut window),
+            Some(_) => settings.video.apply(&mut window),
+        }
+        *applied = Some(settings.video.clone());
+    }
+}
+
+fn pause_on_focus_loss(
+    settings: Res<Settings>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let lost_focus = focus_events.read().any(|event| !event.focused);
+    if lost_focus && settings.gameplay.pause_on_focus_loss {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component, Clone, Copy)]
+enum VolumeSlider {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeSlider {
+    fn label(&self) -> &'static str {
+        match self {
+            VolumeSlider::Master => "Master volume",
+            VolumeSlider::Music => "Music volume",
+            VolumeSlider::Sfx => "Effects volume",
+        }
+    }
+
+    fn volume_mut<'a>(&self, settings: &'a mut AudioSettings) -> &'a mut f32 {
+        match self {
+            VolumeSlider::Master => &mut settings.master_volume,
+            VolumeSlider::Music => &mut settings.music_volume,
+            VolumeSlider::Sfx => &mut settings.sfx_volume,
+        }
+    }
+
+    fn volume(&self, settings: &AudioSettings) -> f32 {
+        match self {
+            VolumeSlider::Master => settings.master_volume,
+            VolumeSlider::Music => settings.music_volume,
+            VolumeSlider::Sfx => settings.sfx_volume,
+        }
+    }
+}
+
+/// The filled part of a [`VolumeSlider`].
+#[derive(Component)]
+struct SliderFill(VolumeSlider);
+
+#[derive(Component, Clone, Copy)]
+enum SettingsButton {
+    Fullscreen,
+    Resolution,
+    VSync,
+    PauseOnFocusLoss,
+    FriendlyFire,
+    ScreenShake,
+    CameraZoom,
+    Trajectories,
+    Recoil,
+    CollisionDamage,
+    Back,
+}
+
+impl SettingsButton {
+    fn label(&self) -> &'static str {
+        match self {
+            SettingsButton::Fullscreen => "Fullscreen",
+            SettingsButton::Resolution => "Resolution",
+            SettingsButton::VSync => "VSync",
+            SettingsButton::PauseOnFocusLoss => "Pause on focus loss",
+            SettingsButton::FriendlyFire => "Friendly fire",
+            SettingsButton::ScreenShake => "Screen shake",
+            SettingsButton::CameraZoom => "Zoom on ships",
+            SettingsButton::Trajectories => "Show trajectories",
+            SettingsButton::Recoil => "Recoil",
+            SettingsButton::CollisionDamage => "Collision damage",
+            SettingsButton::Back => "Back",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        let on_off = |value: bool| if value { "On" } else { "Off" }.to_string();
+        match self {
+            SettingsButton::Fullscreen => on_off(settings.video.fullscreen),
+            SettingsButton::Resolution => {
+                let (width, height) = settings.video.resolution;
+                format!("{width}x{height}")
+            }
+            SettingsButton::VSync => on_off(settings.video.vsync),
+            SettingsButton::PauseOnFocusLoss => on_off(settings.gameplay.pause_on_focus_loss),
+            SettingsButton::FriendlyFire => on_off(settings.gameplay.friendly_fire),
+            SettingsButton::ScreenShake => on_off(settings.gameplay.screen_shake),
+            SettingsButton::CameraZoom => on_off(settings.gameplay.camera_zoom),
+            SettingsButton::Trajectories => on_off(settings.gameplay.trajectories),
+            SettingsButton::Recoil => on_off(settings.gameplay.recoil),
+            SettingsButton::CollisionDamage => on_off(settings.gameplay.collision_damage),
+            SettingsButton::Back => self.label().to_string(),
+        }
+    }
+
+    fn press(&self, settings: &mut Settings) {
+        match self {
+            SettingsButton::Fullscreen => settings.video.fullscreen = !settings.video.fullscreen,
+            SettingsButton::Resolution => {
+                let current = RESOLUTIONS
+                    .iter()
+                    .position(|resolution| *resolution == settings.video.resolution);
+                let next = current.map_or(0, |index| (index + 1) % RESOLUTIONS.len());
+                settings.video.resolution = RESOLUTIONS[next];
+            }
+            SettingsButton::VSync => settings.video.vsync = !settings.video.vsync,
+            SettingsButton::PauseOnFocusLoss => {
+                settings.gameplay.pause_on_focus_loss = !settings.gameplay.pause_on_focus_loss
+            }
+            SettingsButton::FriendlyFire => {
+                settings.gameplay.friendly_fire = !settings.gameplay.friendly_fire
+            }
+            SettingsButton::ScreenShake => {
+                settings.gameplay.screen_shake = !settings.gameplay.screen_shake
+            }
+            SettingsButton::CameraZoom => {
+                settings.gameplay.camera_zoom = !settings.gameplay.camera_zoom
+            }
+            SettingsButton::Trajectories => {
+                settings.gameplay.trajectories = !settings.gameplay.trajectories
+            }
+            SettingsButton::Recoil => settings.gameplay.recoil = !settings.gameplay.recoil,
+            SettingsButton::CollisionDamage => {
+                settings.gameplay.collision_damage = !settings.gameplay.collision_damage
+            }
+            SettingsButton::Back => {}
+        }
+    }
+}
+
+/// Text showing the current value of a [`SettingsButton`].
+#[derive(Component)]
+struct SettingValue(SettingsButton);
+
+fn text_style(font_size: f32) -> TextStyle {
+    TextStyle {
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..default()
+    }
+}
+
+fn row_style() -> Style {
+    Style {
+        width: Val::Px(520.),
+        flex_direction: FlexDirection::Row,
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::SpaceBetween,
+        ..default()
+    }
+}
+
+fn setup_settings_menu(mut commands: Commands, settings: Res<Settings>) {
+    let mut buttons = vec![];
+    if WINDOW_CONFIGURABLE {
+        buttons.extend([
+            SettingsButton::Fullscreen,
+            SettingsButton::Resolution,
+            SettingsButton::VSync,
+        ]);
+    }
+    buttons.extend([
+        SettingsButton::PauseOnFocusLoss,
+        SettingsButton::FriendlyFire,
+        SettingsButton::ScreenShake,
+        SettingsButton::CameraZoom,
+        SettingsButton::Trajectories,
+        SettingsButton::Recoil,
+        SettingsButton::CollisionDamage,
+    ]);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(6.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                ..default()
+            },
+            SettingsMenu,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Settings", text_style(60.)));
+            for slider in [VolumeSlider::Master, VolumeSlider::Music, VolumeSlider::Sfx] {
+                let value = slider.volume(&settings.audio);
+                children
+                    .spawn(NodeBundle {
+                        style: row_style(),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(TextBundle::from_section(slider.label(), text_style(30.)));
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(200.0),
+                                    height: Val::Px(20.0),
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                ..default()
+                            },
+                            RelativeCursorPosition::default(),
+                            slider,
+                        ))
+                        .with_children(|track| {
+                            track.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(value * 100.),
+                                        height: Val::Percent(100.),
+                                        ..default()
+                                    },
+                                    background_color: Color::rgb(0.6, 0.6, 0.6).into(),
+                                    ..default()
+                                },
+                                SliderFill(slider),
+                            ));
+                        });
+                    });
+            }
+            for button in buttons {
+                children
+                    .spawn(NodeBundle {
+                        style: row_style(),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(TextBundle::from_section(button.label(), text_style(30.)));
+                        spawn_button(row, button, &settings);
+                    });
+            }
+            spawn_button(children, SettingsButton::Back, &settings);
+        });
+}
+
+fn spawn_button(parent: &mut ChildBuilder, button: SettingsButton, settings: &Settings) {
+    let button_colors = ButtonColors::default();
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(200.0),
+                    height: Val::Px(36.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: button_colors.normal.into(),
+                ..default()
+            },
+            button_colors,
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(button.value(settings), text_style(30.)),
+                SettingValue(button),
+            ));
+        });
+}
+
+fn click_settings_button(
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+    match_entities: Query<(), With<MatchEntity>>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            &SettingsButton,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                if let SettingsButton::Back = button {
+                    // Go back to wherever the settings were opened from
+                    if match_entities.is_empty() {
+                        next_state.set(GameState::Menu);
+                    } else {
+                        next_state.set(GameState::Paused);
+                    }
+                } else {
+                    button.press(&mut settings);
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+fn drag_volume_slider(
+    mut settings: ResMut<Settings>,
+    slider_query: Query<(&Interaction, &RelativeCursorPosition, &VolumeSlider)>,
+) {
+    for (interaction, cursor_position, slider) in &slider_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(cursor_position) = cursor_position.normalized else {
+            continue;
+        };
+        let value = cursor_position.x.clamp(0., 1.);
+        // Only touch the settings when the value actually changes, to keep change detection meaningful
+        if slider.volume(&settings.audio) != value {
+            *slider.volume_mut(&mut settings.audio) = value;
+        }
+    }
+}
+
+fn update_settings_menu(
+    settings: Res<Settings>,
+    mut fill_query: Query<(&mut Style, &SliderFill)>,
+    mut text_query: Query<(&mut Text, &SettingValue)>,
+) {
+    for (mut style, fill) in &mut fill_query {
+        let value = fill.0.volume(&settings.audio);
+        style.width = Val::Percent(value * 100.);
+    }
+    for (mut text, setting_value) in &mut text_query {
+        text.sections[0].value = setting_value.0.value(&settings);
+    }
+}
+
+fn cleanup_settings_menu(mut commands: Commands, menu: Query<Entity, With<SettingsMenu>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
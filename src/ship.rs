@@ -0,0 +1,214 @@
+use crate::loading::{RonAsset, RonAssetLoader, ShipAssets};
+use crate::player::MAX_PLAYERS;
+use crate::GameState;
+use bevy::asset::LoadContext;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct ShipPlugin;
+
+/// This plugin loads ship classes from `.ship.ron` files
+/// and keeps track of the class every player picked.
+impl Plugin for ShipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ShipClass>()
+            .init_asset_loader::<RonAssetLoader<ShipClass>>()
+            .add_systems(
+                OnExit(GameState::Loading),
+                select_first_ships.run_if(resource_exists::<ShipAssets>()),
+            );
+    }
+}
+
+/// How a kind of ship handles and what it's armed with.
+/// See `assets/ships` for examples.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct ShipClass {
+    pub name: String,
+    pub mass: f32,
+    /// Force of the engine, in Newtons.
+    pub thrust: f32,
+    /// Force of the retro thrusters, half the `thrust` if not set.
+    #[serde(default)]
+    pub reverse_thrust: Option<f32>,
+    /// Force of the side thrusters. Ships without them can't strafe.
+    #[serde(default)]
+    pub strafe_thrust: Option<f32>,
+    /// Radians per second.
+    pub turn_rate: f32,
+    pub collider: Vec2,
+    /// Ships with a restitution bounce off each other instead of crashing, see
+    /// [`crate::physics::Collider`].
+    #[serde(default)]
+    pub restitution: Option<f32>,
+    /// Path of the ship's image, relative to the `assets` folder.
+    pub sprite: String,
+    pub sprite_size: Vec2,
+    #[serde(skip)]
+    #[dependency]
+    pub texture: Handle<Image>,
+    /// Every weapon fires at the same time.
+    pub weapons: Vec<Weapon>,
+    /// Ships without a tank never run out of fuel.
+    #[serde(default)]
+    pub fuel: Option<FuelTank>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Weapon {
+    /// Seconds between shots.
+    pub cooldown: f32,
+    pub muzzle_speed: f32,
+    pub projectile_mass: f32,
+    pub projectile_radius: f32,
+    /// Where projectiles appear, relative to the ship pointing up.
+    pub muzzle_offset: Vec2,
+}
+
+/// Changes made to the weapons of every ship for a whole match.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WeaponSet {
+    /// Every class keeps its own weapons.
+    #[default]
+    Standard,
+    /// Twice the rate of fire, with projectiles half as heavy.
+    Rapid,
+    /// Slow, heavy projectiles fired half as often.
+    Heavy,
+}
+
+impl WeaponSet {
+    pub fn cycle(&mut self) {
+        *self = match self {
+            WeaponSet::Standard => WeaponSet::Rapid,
+            WeaponSet::Rapid => WeaponSet::Heavy,
+            WeaponSet::Heavy => WeaponSet::Standard,
+        };
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WeaponSet::Standard => "Standard",
+            WeaponSet::Rapid => "Rapid fire",
+            WeaponSet::Heavy => "Heavy",
+        }
+    }
+
+    /// `weapon` as it's used in matches with this weapon set.
+    pub fn apply(&self, weapon: &Weapon) -> Weapon {
+        let mut weapon = weapon.clone();
+        match self {
+            WeaponSet::Standard => {}
+            WeaponSet::Rapid => {
+                weapon.cooldown /= 2.;
+                weapon.projectile_mass /= 2.;
+            }
+            WeaponSet::Heavy => {
+                weapon.cooldown *= 2.;
+                weapon.muzzle_speed *= 0.75;
+                weapon.projectile_mass *= 3.;
+                weapon.projectile_radius *= 1.5;
+            }
+        }
+        weapon
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct FuelTank {
+    /// Seconds of thrust on a full tank.
+    pub capacity: f32,
+    /// Seconds of thrust refilled every second the engine is off.
+    pub refill_rate: f32,
+}
+
+impl ShipClass {
+    pub fn reverse_thrust(&self) -> f32 {
+        self.reverse_thrust.unwrap_or(self.thrust / 2.)
+    }
+}
+
+impl RonAsset for ShipClass {
+    const EXTENSIONS: &'static [&'static str] = &["ship.ron"];
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("mass", self.mass),
+            ("thrust", self.thrust),
+            ("turn_rate", self.turn_rate),
+        ] {
+            if value <= 0. {
+                return Err(format!("{name} must be positive, got {value}"));
+            }
+        }
+        for (name, value) in [
+            ("reverse_thrust", self.reverse_thrust),
+            ("strafe_thrust", self.strafe_thrust),
+        ] {
+            if let Some(value) = value.filter(|value| *value <= 0.) {
+                return Err(format!("{name} must be positive, got {value}"));
+            }
+        }
+        if let Some(restitution) = self.restitution {
+            if !(0. ..=1.).contains(&restitution) {
+                return Err(format!(
+                    "restitution must be between 0 and 1, got {restitution}"
+                ));
+            }
+        }
+        if self.collider.cmple(Vec2::ZERO).any() || self.sprite_size.cmple(Vec2::ZERO).any() {
+            return Err("collider and sprite_size must be positive".to_string());
+        }
+        if self.weapons.is_empty() {
+            return Err("needs at least one weapon".to_string());
+        }
+        for (index, weapon) in self.weapons.iter().enumerate() {
+            if weapon.cooldown <= 0.
+                || weapon.muzzle_speed <= 0.
+                || weapon.projectile_mass <= 0.
+                || weapon.projectile_radius <= 0.
+            {
+                return Err(format!(
+                    "weapon {index} must have a positive cooldown, muzzle_speed, projectile_mass and projectile_radius"
+                ));
+            }
+        }
+        if let Some(fuel) = &self.fuel {
+            if fuel.capacity <= 0. || fuel.refill_rate < 0. {
+                return Err(
+                    "fuel needs a positive capacity and a refill_rate of at least 0".to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn load_dependencies(&mut self, load_context: &mut LoadContext) {
+        self.texture = load_context.load(&self.sprite);
+    }
+}
+
+/// The ship class of every player that could be in a match, in player order.
+#[derive(Resource)]
+pub struct SelectedShips(pub Vec<Handle<ShipClass>>);
+
+impl SelectedShips {
+    /// Moves the selection of a player `step` classes forward (or backwards if negative),
+    /// wrapping around at both ends.
+    pub fn cycle(&mut self, player_index: usize, step: isize, ship_assets: &ShipAssets) {
+        let classes = &ship_assets.classes;
+        let current = classes
+            .iter()
+            .position(|class| *class == self.0[player_index])
+            .unwrap_or(0);
+        let next = (current as isize + step).rem_euclid(classes.len() as isize);
+        self.0[player_index] = classes[next as usize].clone();
+    }
+}
+
+fn select_first_ships(mut commands: Commands, ship_assets: Res<ShipAssets>) {
+    commands.insert_resource(SelectedShips(vec![
+        ship_assets.classes[0].clone();
+        MAX_PLAYERS
+    ]));
+}
@@ -0,0 +1,449 @@
+use crate::actions::{GameControl, Pilot, PlayerInput};
+use crate::loading::ShipAssets;
+use crate::match_setup::MatchConfig;
+use crate::menu::ButtonColors;
+use crate::player::{player_color, FlightAssists, PlayerCount};
+use crate::ship::{SelectedShips, ShipClass};
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct ShipSelectPlugin;
+
+/// This plugin draws the screen where every player picks a ship class before a match.
+/// Players cycle through the classes with their turn controls, toggle flight assist
+/// with reverse thrust and fire when they're ready, the match starts once everybody is.
+/// Players flown by the computer start out ready, their ships can still be changed with the mouse.
+impl Plugin for ShipSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::ShipSelect), setup_ship_select)
+            .add_systems(
+                Update,
+                (
+                    select_with_controls,
+                    click_ship_select_button,
+                    update_ship_columns,
+                    start_when_ready,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::ShipSelect)),
+            )
+            .add_systems(OnExit(GameState::ShipSelect), cleanup_ship_select);
+    }
+}
+
+#[derive(Component)]
+struct ShipSelectScreen;
+
+/// Which players confirmed their pick.
+#[derive(Resource)]
+struct ReadyPlayers(Vec<bool>);
+
+/// Text describing the class picked by a player, by player index.
+#[derive(Component)]
+struct ShipDescription(usize);
+
+/// Text showing whether a player is ready, by player index.
+#[derive(Component)]
+struct ReadyLabel(usize);
+
+/// Text showing whether a player flies with flight assist, by player index.
+#[derive(Component)]
+struct AssistLabel(usize);
+
+#[derive(Component, Clone, Copy)]
+enum ShipSelectButton {
+    Previous(usize),
+    Next(usize),
+    Ready(usize),
+    Assist(usize),
+    Start,
+    Back,
+}
+
+fn describe(ship_class: Option<&ShipClass>) -> String {
+    let Some(ship_class) = ship_class else {
+        return "?".to_string();
+    };
+    let fuel = ship_class.fuel.map_or("unlimited".to_string(), |tank| {
+        format!("{:.1} s", tank.capacity)
+    });
+    let strafe = ship_class
+        .strafe_thrust
+        .map_or("none".to_string(), |thrust| format!("{thrust:.0}"));
+    format!(
+        "{}\n\nMass: {:.1}\nThrust: {:.0}\nReverse: {:.0}\nStrafe: {strafe}\nTurn rate: {:.1}\nGuns: {}\nFuel: {fuel}",
+        ship_class.name,
+        ship_class.mass,
+        ship_class.thrust,
+        ship_class.reverse_thrust(),
+        ship_class.turn_rate,
+        ship_class.weapons.len(),
+    )
+}
+
+fn ready_label(ready: bool) -> &'static str {
+    if ready {
+        "Ready!"
+    } else {
+        "Not ready"
+    }
+}
+
+fn assist_label(assisted: bool) -> &'static str {
+    if assisted {
+        "Assist: On"
+    } else {
+        "Assist: Off"
+    }
+}
+
+fn spawn_button(children: &mut ChildBuilder, button: ShipSelectButton, label: &str, width: f32) {
+    let button_colors = ButtonColors::default();
+    children
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(width),
+                    height: Val::Px(50.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: button_colors.normal.into(),
+                ..Default::default()
+            },
+            button_colors,
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 30.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// A button whose text changes, found through the `label` component.
+fn spawn_labelled_button(
+    children: &mut ChildBuilder,
+    button: ShipSelectButton,
+    (text, label): (&str, impl Component),
+    text_style: &TextStyle,
+) {
+    children
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: ButtonColors::default().normal.into(),
+                ..Default::default()
+            },
+            ButtonColors::default(),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font_size: 30.0,
+                        ..text_style.clone()
+                    },
+                ),
+                label,
+            ));
+        });
+}
+
+fn setup_ship_select(
+    mut commands: Commands,
+    selected_ships: Res<SelectedShips>,
+    ship_classes: Res<Assets<ShipClass>>,
+    player_count: Res<PlayerCount>,
+    flight_assists: Res<FlightAssists>,
+    config: Res<MatchConfig>,
+) {
+    commands.insert_resource(ReadyPlayers(
+        config.pilots[..player_count.0]
+            .iter()
+            .map(|pilot| *pilot == Pilot::Ai)
+            .collect(),
+    ));
+    let text_style = TextStyle {
+        font_size: 25.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            ShipSelectScreen,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Choose your ships",
+                TextStyle {
+                    font_size: 50.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+            children.spawn(TextBundle::from_section(
+                "Turn to change ship, reverse to toggle flight assist, fire when ready",
+                text_style.clone(),
+            ));
+            children
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(30.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|columns| {
+                    for (player_index, ship) in
+                        selected_ships.0.iter().take(player_count.0).enumerate()
+                    {
+                        columns
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    align_items: AlignItems::Center,
+                                    row_gap: Val::Px(10.),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|column| {
+                                let pilot = match config.pilots[player_index] {
+                                    Pilot::Human => "",
+                                    Pilot::Ai => " (AI)",
+                                };
+                                column.spawn(TextBundle::from_section(
+                                    format!("Player {}{pilot}", player_index + 1),
+                                    TextStyle {
+                                        font_size: 35.0,
+                                        color: player_color(player_index),
+                                        ..default()
+                                    },
+                                ));
+                                column
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            flex_direction: FlexDirection::Row,
+                                            align_items: AlignItems::Center,
+                                            column_gap: Val::Px(10.),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    })
+                                    .with_children(|row| {
+                                        spawn_button(
+                                            row,
+                                            ShipSelectButton::Previous(player_index),
+                                            "<",
+                                            50.,
+                                        );
+                                        row.spawn((
+                                            TextBundle::from_section(
+                                                describe(ship_classes.get(ship)),
+                                                text_style.clone(),
+                                            )
+                                            .with_style(Style {
+                                                width: Val::Px(180.),
+                                                ..default()
+                                            }),
+                                            ShipDescription(player_index),
+                                        ));
+                                        spawn_button(
+                                            row,
+                                            ShipSelectButton::Next(player_index),
+                                            ">",
+                                            50.,
+                                        );
+                                    });
+                                spawn_labelled_button(
+                                    column,
+                                    ShipSelectButton::Assist(player_index),
+                                    (
+                                        assist_label(flight_assists.0[player_index]),
+                                        AssistLabel(player_index),
+                                    ),
+                                    &text_style,
+                                );
+                                spawn_labelled_button(
+                                    column,
+                                    ShipSelectButton::Ready(player_index),
+                                    (ready_label(false), ReadyLabel(player_index)),
+                                    &text_style,
+                                );
+                            });
+                    }
+                });
+            children
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(20.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_button(row, ShipSelectButton::Back, "Back", 200.);
+                    spawn_button(row, ShipSelectButton::Start, "Start", 200.);
+                });
+        });
+}
+
+/// Lets every player pick with their own keys, so they can all choose at the same time.
+fn select_with_controls(
+    input: PlayerInput,
+    mut selected_ships: ResMut<SelectedShips>,
+    mut ready_players: ResMut<ReadyPlayers>,
+    mut flight_assists: ResMut<FlightAssists>,
+    ship_assets: Res<ShipAssets>,
+    config: Res<MatchConfig>,
+) {
+    for player_index in 0..ready_players.0.len() {
+        if config.pilots[player_index] == Pilot::Ai {
+            continue;
+        }
+        let player_number = player_index as u8 + 1;
+        if input.just_pressed(GameControl::Fire, player_number) {
+            ready_players.0[player_index] = !ready_players.0[player_index];
+        }
+        // Changing anything takes the player out of ready, like the buttons do
+        if input.just_pressed(GameControl::Left, player_number) {
+            selected_ships.cycle(player_index, -1, &ship_assets);
+            ready_players.0[player_index] = false;
+        }
+        if input.just_pressed(GameControl::Right, player_number) {
+            selected_ships.cycle(player_index, 1, &ship_assets);
+            ready_players.0[player_index] = false;
+        }
+        if input.just_pressed(GameControl::Down, player_number) {
+            flight_assists.0[player_index] = !flight_assists.0[player_index];
+            ready_players.0[player_index] = false;
+        }
+    }
+}
+
+fn click_ship_select_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut selected_ships: ResMut<SelectedShips>,
+    mut ready_players: ResMut<ReadyPlayers>,
+    mut flight_assists: ResMut<FlightAssists>,
+    ship_assets: Res<ShipAssets>,
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &ButtonColors,
+            &ShipSelectButton,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match *button {
+                ShipSelectButton::Previous(player_index) => {
+                    selected_ships.cycle(player_index, -1, &ship_assets);
+                    ready_players.0[player_index] = false;
+                }
+                ShipSelectButton::Next(player_index) => {
+                    selected_ships.cycle(player_index, 1, &ship_assets);
+                    ready_players.0[player_index] = false;
+                }
+                ShipSelectButton::Ready(player_index) => {
+                    ready_players.0[player_index] = !ready_players.0[player_index];
+                }
+                ShipSelectButton::Assist(player_index) => {
+                    flight_assists.0[player_index] = !flight_assists.0[player_index];
+                    ready_players.0[player_index] = false;
+                }
+                ShipSelectButton::Start => next_state.set(GameState::Playing),
+                ShipSelectButton::Back => next_state.set(GameState::MatchSetup),
+            },
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+        }
+    }
+}
+
+fn update_ship_columns(
+    selected_ships: Res<SelectedShips>,
+    ready_players: Res<ReadyPlayers>,
+    flight_assists: Res<FlightAssists>,
+    ship_classes: Res<Assets<ShipClass>>,
+    mut descriptions: Query<
+        (&mut Text, &ShipDescription),
+        (Without<ReadyLabel>, Without<AssistLabel>),
+    >,
+    mut ready_labels: Query<
+        (&mut Text, &ReadyLabel),
+        (Without<ShipDescription>, Without<AssistLabel>),
+    >,
+    mut assist_labels: Query<
+        (&mut Text, &AssistLabel),
+        (Without<ShipDescription>, Without<ReadyLabel>),
+    >,
+) {
+    if selected_ships.is_changed() {
+        for (mut text, description) in &mut descriptions {
+            text.sections[0].value = describe(ship_classes.get(&selected_ships.0[description.0]));
+        }
+    }
+    if ready_players.is_changed() {
+        for (mut text, label) in &mut ready_labels {
+            text.sections[0].value = ready_label(ready_players.0[label.0]).to_string();
+        }
+    }
+    if flight_assists.is_changed() {
+        for (mut text, label) in &mut assist_labels {
+            text.sections[0].value = assist_label(flight_assists.0[label.0]).to_string();
+        }
+    }
+}
+
+fn start_when_ready(
+    ready_players: Res<ReadyPlayers>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if ready_players.is_changed() && ready_players.0.iter().all(|ready| *ready) {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn cleanup_ship_select(mut commands: Commands, screen: Query<Entity, With<ShipSelectScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<ReadyPlayers>();
+}
@@ -0,0 +1,71 @@
+use crate::player::player_color;
+use bevy::prelude::*;
+
+/// Whether players fight on their own or in teams.
+/// Points go to a "side": the player in free-for-all matches, their team otherwise.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TeamMode {
+    #[default]
+    FreeForAll,
+    /// Odd players against even players.
+    Teams,
+}
+
+const TEAM_COUNT: usize = 2;
+const TEAM_NAMES: [&str; TEAM_COUNT] = ["Red", "Blue"];
+const TEAM_COLORS: [Color; TEAM_COUNT] = [Color::rgb(1.0, 0.35, 0.3), Color::rgb(0.35, 0.6, 1.0)];
+
+/// The team a ship or projectile belongs to, only present in team matches.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Team(pub usize);
+
+impl TeamMode {
+    pub fn cycle(&mut self) {
+        *self = match self {
+            TeamMode::FreeForAll => TeamMode::Teams,
+            TeamMode::Teams => TeamMode::FreeForAll,
+        };
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TeamMode::FreeForAll => "Free for all",
+            TeamMode::Teams => "Teams",
+        }
+    }
+
+    pub fn team(&self, player_index: usize) -> Option<Team> {
+        match self {
+            TeamMode::FreeForAll => None,
+            TeamMode::Teams => Some(Team(player_index % TEAM_COUNT)),
+        }
+    }
+
+    /// The side a player scores for.
+    pub fn side(&self, player_index: usize) -> usize {
+        self.team(player_index).map_or(player_index, |team| team.0)
+    }
+
+    /// How many sides a match with `player_count` players has.
+    pub fn sides(&self, player_count: usize) -> usize {
+        match self {
+            TeamMode::FreeForAll => player_count,
+            TeamMode::Teams => player_count.min(TEAM_COUNT),
+        }
+    }
+
+    pub fn side_name(&self, side: usize) -> String {
+        match self {
+            TeamMode::FreeForAll => format!("Player {}", side + 1),
+            TeamMode::Teams => format!("{} team", TEAM_NAMES[side]),
+        }
+    }
+
+    /// Ships and everything they fire are tinted with the color of their side.
+    pub fn side_color(&self, side: usize) -> Color {
+        match self {
+            TeamMode::FreeForAll => player_color(side),
+            TeamMode::Teams => TEAM_COLORS[side],
+        }
+    }
+}
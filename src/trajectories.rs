@@ -0,0 +1,129 @@
+use crate::boundaries::Arena;
+use crate::match_setup::MatchConfig;
+use crate::physics::portals::Portal;
+use crate::physics::{gravity, integrate, Collider, Mass, Star, Velocity};
+use crate::player::{launch, Guns, Player};
+use crate::{GameState, Settings};
+use bevy::prelude::*;
+
+pub struct TrajectoriesPlugin;
+
+/// This plugin draws where every ship is headed if it stops thrusting,
+/// and where a shot fired right now would go, to help learning to fly in orbit.
+/// It's enabled in the gameplay settings.
+impl Plugin for TrajectoriesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            draw_trajectories
+                .run_if(|settings: Res<Settings>| settings.gameplay.trajectories)
+                .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+        );
+    }
+}
+
+/// How far ahead paths are predicted.
+const PREDICTION_SECONDS: f32 = 3.;
+/// Predictions are integrated in steps this long, about one frame.
+const PREDICTION_STEP: f32 = 1. / 60.;
+const SHIP_PATH_ALPHA: f32 = 0.5;
+const SHOT_PATH_ALPHA: f32 = 0.25;
+
+/// A star as the prediction sees it: its mass, position and collider.
+type PredictedStar = (f32, Vec3, Vec2);
+
+/// A portal as the prediction sees it: its position and radius.
+type PredictedPortal = (Vec3, f32);
+
+/// Integrates the motion of a body under the gravity of `stars` alone,
+/// stopping early if it leaves the arena, hits a star or enters a portal.
+fn predict_path(
+    mut position: Vec3,
+    mut velocity: Vec3,
+    mass: f32,
+    stars: &[PredictedStar],
+    portals: &[PredictedPortal],
+    arena: &Arena,
+) -> Vec<Vec2> {
+    // A body that just came out of a portal is still inside it
+    let portals: Vec<&PredictedPortal> = portals
+        .iter()
+        .filter(|(portal_position, radius)| position.distance(*portal_position) > *radius)
+        .collect();
+    let steps = (PREDICTION_SECONDS / PREDICTION_STEP) as usize;
+    let mut path = Vec::with_capacity(steps + 1);
+    path.push(position.truncate());
+    for _ in 0..steps {
+        let force = gravity(
+            position,
+            mass,
+            stars
+                .iter()
+                .map(|(star_mass, star_position, _)| (*star_mass, *star_position)),
+        );
+        integrate(&mut position, &mut velocity, force / mass, PREDICTION_STEP);
+        path.push(position.truncate());
+        let inside_star = stars.iter().any(|(_, star_position, collider)| {
+            (position - *star_position)
+                .truncate()
+                .abs()
+                .cmplt(*collider / 2.)
+                .all()
+        });
+        let inside_portal = portals
+            .iter()
+            .any(|(portal_position, radius)| position.distance(*portal_position) <= *radius);
+        if inside_star || inside_portal || position.truncate().abs().cmpgt(arena.half_size).any() {
+            break;
+        }
+    }
+    path
+}
+
+fn draw_trajectories(
+    mut gizmos: Gizmos,
+    ships: Query<(&Transform, &Velocity, &Mass, &Guns, &Sprite), With<Player>>,
+    stars: Query<(&Mass, &Transform, Option<&Collider>), With<Star>>,
+    portals: Query<(&Portal, &Transform)>,
+    arena: Res<Arena>,
+    config: Res<MatchConfig>,
+) {
+    let portals: Vec<PredictedPortal> = portals
+        .iter()
+        .map(|(portal, transform)| (transform.translation, portal.radius))
+        .collect();
+    let stars: Vec<PredictedStar> = stars
+        .iter()
+        .map(|(mass, transform, collider)| {
+            (
+                mass.0 * config.gravity,
+                transform.translation,
+                collider.map_or(Vec2::ZERO, |collider| collider.dimensions),
+            )
+        })
+        .collect();
+    for (transform, velocity, mass, guns, sprite) in &ships {
+        let path = predict_path(
+            transform.translation,
+            velocity.0,
+            mass.0,
+            &stars,
+            &portals,
+            &arena,
+        );
+        gizmos.linestrip_2d(path, sprite.color.with_a(SHIP_PATH_ALPHA));
+        // The first gun is enough to show where shots go
+        if let Some(weapon) = guns.weapons().next() {
+            let (position, shot_velocity) = launch(weapon, transform, velocity.0);
+            let path = predict_path(
+                position,
+                shot_velocity,
+                weapon.projectile_mass,
+                &stars,
+                &portals,
+                &arena,
+            );
+            gizmos.linestrip_2d(path, sprite.color.with_a(SHOT_PATH_ALPHA));
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Checks the Barnes-Hut approximation against the exact sum of every attraction.
+
+mod common;
+
+use bevy::math::Vec2;
+use common::random_bodies;
+use star_fighters::physics::barnes_hut::{exact_gravity, Body, QuadTree, THETA};
+
+/// Root mean square error of the approximated forces, relative to the exact ones.
+fn relative_error(bodies: &[Body], theta: f32) -> f32 {
+    let tree = QuadTree::new(bodies);
+    let (error, magnitude) = bodies.iter().fold((0., 0.), |(error, magnitude), body| {
+        let exact = exact_gravity(bodies, body.position);
+        let approximated = tree.gravity(body.position, theta);
+        (
+            error + (approximated - exact).length_squared(),
+            magnitude + exact.length_squared(),
+        )
+    });
+    (error / magnitude).sqrt()
+}
+
+#[test]
+fn matches_exact_sum_without_approximating() {
+    let bodies = random_bodies(200);
+    assert!(relative_error(&bodies, 0.) < 1e-4);
+}
+
+#[test]
+fn stays_close_to_exact_sum() {
+    let bodies = random_bodies(2_000);
+    let error = relative_error(&bodies, THETA);
+    assert!(error < 0.01, "relative error {error} is too high");
+}
+
+#[test]
+fn ignores_overlapping_bodies() {
+    let body = Body {
+        position: Vec2::new(10., 20.),
+        mass: 1.,
+    };
+    let tree = QuadTree::new(&[body, body]);
+    assert_eq!(tree.gravity(body.position, THETA), Vec2::ZERO);
+}
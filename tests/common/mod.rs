@@ -0,0 +1,18 @@
+//! Fixtures shared by the tests and the benchmarks, which include this file by path.
+
+use bevy::math::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use star_fighters::physics::barnes_hut::Body;
+
+/// `count` bodies scattered over an arena the size of the bigger levels,
+/// the same ones every run.
+pub fn random_bodies(count: usize) -> Vec<Body> {
+    let mut rng = StdRng::seed_from_u64(39);
+    (0..count)
+        .map(|_| Body {
+            position: Vec2::new(rng.gen_range(-725.0..725.), rng.gen_range(-350.0..350.)),
+            mass: rng.gen_range(0.1..2.),
+        })
+        .collect()
+}